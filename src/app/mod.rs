@@ -0,0 +1,1628 @@
+use crate::models::*;
+use crate::config;
+use crate::cli;
+use crate::core::{
+    self,
+    DATE_FORMAT,
+    TIME_FORMAT,
+    parse_due_date,
+    parse_due_time,
+    parse_recurrence,
+    parse_tags,
+};
+#[cfg(unix)]
+use crate::rpc;
+use crate::server;
+use crate::storage;
+use crate::migrations;
+#[cfg(feature = "gui")]
+mod view;
+use std::{
+    fs,
+    io::{
+        self, 
+        Read, 
+    },
+    path::{
+        Path, 
+        PathBuf,
+    },
+};
+use std::hash::{Hash, Hasher};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+};
+use chrono::{
+    NaiveDate,
+    NaiveTime,
+};
+
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// How many steps Ctrl+Z can go back.
+const UNDO_LIMIT: usize = 100;
+
+#[cfg(not(debug_assertions))]
+fn logs_path() -> Option<PathBuf> {
+    config::state_dir().map(|dir| dir.join("logs.txt"))
+}
+
+#[cfg(debug_assertions)]
+#[inline]
+fn log_error(msg: &str) {
+    eprintln!("{msg}");
+}
+
+#[cfg(not(debug_assertions))]
+fn log_error(msg: &str) {
+    use std::io::Write;
+    if let Some(path) = logs_path() {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let timestamp = chrono::Local::now()
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let line = format!("[{}] {}\n", timestamp, msg);
+        let mut file = match std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+        {
+            Ok(f) => f,
+            Err(_) => return,
+        };
+        let _ = file.write_all(line.as_bytes());
+    }
+}
+
+impl Message {
+    /// Toast text for actions that delete data, which get an "Undo" prompt.
+    fn deletion_notice(&self) -> Option<&'static str> {
+        match self {
+            Message::Remove(_) => Some("Task moved to trash"),
+            Message::RemoveSubtask(..) => Some("Subtask moved to trash"),
+            Message::RemoveList(_) => Some("List moved to trash"),
+            Message::InList(_, msg) => msg.deletion_notice(),
+            _ => None,
+        }
+    }
+}
+
+impl UndoHistory {
+    /// Adds a step undone by `changes`. Typing in the notes the latest step
+    /// undoes joins that step instead; `doc` gives the notes as they are
+    /// now. Returns whether a new step was added.
+    fn record(&mut self, changes: Vec<Change>, doc: &List) -> bool {
+        if changes.is_empty() {
+            return false;
+        }
+        self.redo.clear();
+        let notes = match changes.as_slice() {
+            [Change::SpliceNotes { list, id, .. }] => Some((*list, *id)),
+            _ => None,
+        };
+        if let Some((list, id)) = notes
+            && self.coalescing == Some(id)
+            && let Some([older]) = self.undo.back_mut().map(Vec::as_mut_slice) {
+            let text = doc.list(list).and_then(|l| l.task(id)).and_then(|t| t.notes.as_deref());
+            if older.absorb(&changes[0], text.unwrap_or_default()) {
+                return false;
+            }
+        }
+        self.coalescing = notes.map(|(_, id)| id);
+        self.undo.push_back(changes);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.pop_front();
+        }
+        true
+    }
+}
+
+impl App {
+    /// Applies a message aimed at the tasks of `list`, normally the selected
+    /// one. Edits that a core operation rejects keep their input open and
+    /// show why.
+    fn update_list(&mut self, list: ListId, msg: Message) {
+        let editor = &mut self.list_editor;
+        let result = match msg {
+            Message::AddAfter(index) => {
+                editor.adding_after = Some(index);
+                Ok(false)
+            }
+            Message::UpdateNewTitle(title) => {
+                editor.new_title = title;
+                Ok(false)
+            }
+            Message::UpdateNewDueDate(date) => {
+                editor.new_due_date = date;
+                Ok(false)
+            }
+            Message::UpdateNewDueTime(time) => {
+                editor.new_due_time = time;
+                Ok(false)
+            }
+            Message::UpdateNewTags(tags) => {
+                editor.new_tags = tags;
+                Ok(false)
+            }
+            Message::UpdateNewRecurrence(rule) => {
+                editor.new_recurrence = rule;
+                Ok(false)
+            }
+            Message::ConfirmAdd => editor.due().and_then(|(due_date, due_time)| {
+                let task = Task {
+                    due_date,
+                    due_time,
+                    tags: parse_tags(&editor.new_tags),
+                    recurrence: editor.recurrence()?,
+                    ..Task::new(editor.new_title.clone())
+                };
+                self.doc.push_task(list, task).map(|_| {
+                    editor.clear_inputs();
+                    editor.adding_after = None;
+                    true
+                })
+            }),
+            Message::CancelAdd => {
+                editor.clear_inputs();
+                editor.adding_after = None;
+                editor.adding_subtask = None;
+                Ok(false)
+            }
+            Message::Remove(id) => {
+                if editor.notes_open == Some(id) {
+                    editor.notes_open = None;
+                }
+                if editor.history_open == Some(id) {
+                    editor.history_open = None;
+                }
+                self.doc.remove_task(list, id).map(|_| true)
+            }
+            Message::Forward(id) => self.doc.advance_task(list, id).map(|_| true),
+            Message::ChangeTitle(id) => {
+                if let Some(task) = self.doc.list(list).and_then(|l| l.task(id)) {
+                    editor.editing = Some(id);
+                    editor.new_title = task.title.clone();
+                    editor.new_due_date = task.due_date
+                        .map(|d| d.format(DATE_FORMAT).to_string())
+                        .unwrap_or_default();
+                    editor.new_due_time = task.due_time
+                        .map(|t| t.format(TIME_FORMAT).to_string())
+                        .unwrap_or_default();
+                    editor.new_tags = task.tags.join(" ");
+                    editor.new_recurrence = task.recurrence
+                        .as_ref()
+                        .map(Recurrence::to_string)
+                        .unwrap_or_default();
+                }
+                Ok(false)
+            }
+            Message::ConfirmEdit => match editor.editing {
+                Some(id) => editor.due().and_then(|(due_date, due_time)| {
+                    let recurrence = editor.recurrence()?;
+                    self.doc.rename_task(list, id, &editor.new_title)
+                        .and_then(|_| self.doc.set_due(list, id, due_date, due_time))
+                        .and_then(|_| self.doc.set_tags(list, id, parse_tags(&editor.new_tags)))
+                        .and_then(|_| self.doc.set_recurrence(list, id, recurrence))
+                        .map(|_| {
+                            editor.editing = None;
+                            editor.clear_inputs();
+                            true
+                        })
+                }),
+                None => Ok(false),
+            },
+            Message::CancelEdit => {
+                editor.editing = None;
+                editor.clear_inputs();
+                Ok(false)
+            }
+            Message::SetPriority(id, priority) => self.doc.set_priority(list, id, priority).map(|_| true),
+            Message::AddTask(title) => self.doc.add_task(list, &title).map(|_| true),
+            Message::RenameTask(id, title) => self.doc.rename_task(list, id, &title).map(|_| true),
+            Message::SortChanged(order) => self.doc.set_sort_order(list, order).map(|_| true),
+            Message::ToggleTagFilter(tag) => {
+                if let Some(pos) = editor.tag_filter.iter().position(|t| *t == tag) {
+                    editor.tag_filter.remove(pos);
+                } else {
+                    editor.tag_filter.push(tag);
+                }
+                Ok(false)
+            }
+            Message::ClearTagFilter => {
+                editor.tag_filter.clear();
+                Ok(false)
+            }
+            Message::OpenNotes(id) => {
+                if self.doc.list(list).and_then(|l| l.task(id)).is_some() {
+                    editor.notes_open = Some(id);
+                }
+                Ok(false)
+            }
+            Message::NotesChanged(notes) => match editor.notes_open {
+                Some(id) => self.doc.set_notes(list, id, &notes).map(|_| true),
+                None => Ok(false),
+            },
+            Message::CloseNotes => {
+                editor.notes_open = None;
+                Ok(false)
+            }
+            Message::AddSubtask(id) => {
+                if self.doc.list(list).and_then(|l| l.task(id)).is_some() {
+                    editor.adding_subtask = Some(id);
+                    editor.clear_inputs();
+                }
+                Ok(false)
+            }
+            Message::ConfirmAddSubtask => match editor.adding_subtask {
+                Some(id) => self.doc.add_subtask(list, id, &editor.new_title).map(|_| {
+                    editor.adding_subtask = None;
+                    editor.clear_inputs();
+                    true
+                }),
+                None => Ok(false),
+            },
+            Message::ForwardSubtask(id, sub) => self.doc.advance_subtask(list, id, sub).map(|_| true),
+            Message::RemoveSubtask(id, sub) => self.doc.remove_subtask(list, id, sub).map(|_| true),
+            Message::ShowHistory(id) => {
+                if self.doc.list(list).and_then(|l| l.task(id)).is_some() {
+                    editor.history_open = Some(id);
+                }
+                Ok(false)
+            }
+            Message::CloseHistory => {
+                editor.history_open = None;
+                Ok(false)
+            }
+            Message::ToggleCollapsed(id) => self.doc.toggle_collapsed(list, id).map(|_| true),
+            _ => Ok(false),
+        };
+        match result {
+            Ok(changed) => {
+                editor.error = None;
+                if changed {
+                    self.mark_dirty();
+                }
+            }
+            Err(e) => editor.error = Some(e.to_string()),
+        }
+    }
+}
+
+/// The `title` parameter, refused up front if blank.
+#[cfg(unix)]
+fn rpc_title(params: &serde_json::Value) -> Result<String, rpc::Error> {
+    let title = rpc::param(params, "title")?;
+    if title.trim().is_empty() {
+        return Err(core::Error::EmptyTitle.into());
+    }
+    Ok(title)
+}
+
+impl ListEditor {
+    fn clear_inputs(&mut self) {
+        self.new_title.clear();
+        self.new_due_date.clear();
+        self.new_due_time.clear();
+        self.new_tags.clear();
+        self.new_recurrence.clear();
+    }
+
+    /// The due date and time typed into the inputs. Empty inputs mean no
+    /// due date or time; anything else that does not parse is rejected so
+    /// the task keeps the date it had.
+    fn due(&self) -> Result<(Option<NaiveDate>, Option<NaiveTime>), core::Error> {
+        let date = parse_input(&self.new_due_date, parse_due_date, "a due date like 2024-12-31")?;
+        let time = parse_input(&self.new_due_time, parse_due_time, "a due time like 14:30")?;
+        Ok((date, date.and(time)))
+    }
+
+    /// The typed repeat rule; blank means none, and a rule that does not
+    /// parse is rejected rather than dropping the task's recurrence.
+    fn recurrence(&self) -> Result<Option<Recurrence>, core::Error> {
+        parse_input(&self.new_recurrence, parse_recurrence, "a repeat rule like \"weekly mon,fri\", \"monthly 15\" or \"every 3 days\"")
+    }
+}
+
+/// Reads an optional input with `parse`; blank input is `None`.
+fn parse_input<T>(input: &str, parse: fn(&str) -> Option<T>, expected: &str) -> Result<Option<T>, core::Error> {
+    if input.trim().is_empty() {
+        return Ok(None);
+    }
+    parse(input)
+        .map(Some)
+        .ok_or_else(|| core::Error::BadInput(format!("\"{}\" is not {expected}", input.trim())))
+}
+
+impl App {
+    fn ensure_parent_dir(path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Serializes the document along with the backend it should be written
+    /// to and, if only tasks changed since the last save, which ones.
+    fn snapshot(&mut self) -> Option<Snapshot> {
+        if self.read_only {
+            return None;
+        }
+        if self.recovery.as_ref().is_some_and(|r| r.quarantined.is_none()) {
+            log_error("Not saving: the unreadable lists file could not be moved aside.");
+            return None;
+        }
+        let Some(backend) = self.backend.clone() else {
+            log_error("Not saving: the data file could not be opened.");
+            return None;
+        };
+        match serde_json::to_value(&self.doc) {
+            Ok(doc) => {
+                let tasks = self.unsaved_tasks.replace(Vec::new());
+                Some(Snapshot { backend, doc, tasks, recovering: self.recovery.is_some() })
+            }
+            Err(e) => {
+                log_error(&format!("Failed to serialize lists: {e}"));
+                None
+            }
+        }
+    }
+
+    /// Writes a snapshot unless a newer revision has already been written,
+    /// which can happen when a background save finishes after a flush.
+    /// While recovering, the backups stay as they are until the user has
+    /// decided whether to restore one.
+    fn write(snapshot: Snapshot, revision: u64, written: &Mutex<u64>) -> Result<(), String> {
+        let Snapshot { backend, doc, tasks, recovering } = snapshot;
+        let mut written = written.lock().unwrap_or_else(|e| e.into_inner());
+        if revision < *written {
+            return Ok(());
+        }
+        let path = backend.path();
+        match tasks {
+            _ if recovering => backend.save_keeping_backup(&doc),
+            Some(tasks) => backend.save_tasks(&doc, &tasks),
+            None => backend.save(&doc),
+        }
+        .map_err(|e| format!("Failed to write lists to {}: {e}", path.display()))?;
+        *written = revision;
+        if recovering {
+            return Ok(());
+        }
+        storage::rotate_snapshots(backend.as_ref())
+            .map_err(|e| format!("Failed to rotate backups of {}: {e}", path.display()))
+    }
+
+    /// Writes the document immediately, blocking the caller.
+    fn save(&mut self) {
+        // A background save may still be writing an older snapshot, whose
+        // tasks this one would not include.
+        let whole = self.saving;
+        if let Some(mut snapshot) = self.snapshot() {
+            if whole {
+                snapshot.tasks = None;
+            }
+            self.on_disk = Self::disk_state(&self.doc.lists, &self.doc.trash, self.doc.selected_theme);
+            if let Err(e) = Self::write(snapshot, self.revision, &self.written) {
+                self.unsaved_tasks = None;
+                log_error(&e);
+            }
+        }
+    }
+
+    /// Notes which tasks `changes` touched, so the next save can write just
+    /// those; a change to the lists themselves means writing everything.
+    fn note_unsaved(&mut self, changes: &[Change]) {
+        for change in changes {
+            match (change.touches_lists(), change.task(), &mut self.unsaved_tasks) {
+                (false, Some(task), Some(tasks)) => tasks.push(task),
+                (false, None, _) => {}
+                _ => self.unsaved_tasks = None,
+            }
+        }
+    }
+
+    /// Opens the data file that this workspace keeps open until it closes.
+    fn open_backend(path: &Path) -> Option<Arc<dyn storage::Backend>> {
+        match storage::open(path) {
+            Ok(backend) => Some(Arc::from(backend)),
+            Err(e) => {
+                log_error(&format!("Failed to open {}: {e}", path.display()));
+                None
+            }
+        }
+    }
+
+    /// Writes any pending changes right away.
+    pub(crate) fn flush(&mut self) {
+        if self.last_change.take().is_some() || self.saving {
+            self.save();
+        }
+    }
+
+    /// Flushes everything that should survive the window closing.
+    pub(crate) fn close(&mut self) {
+        self.flush();
+        self.save_undo_history();
+    }
+
+    /// Schedules a save once edits have settled for `SAVE_DEBOUNCE`.
+    fn mark_dirty(&mut self) {
+        self.revision += 1;
+        self.last_change = Some(Instant::now());
+    }
+
+    /// Answers a call from the control socket by sending the window the
+    /// same messages its buttons do, so calls are undone and saved alike.
+    #[cfg(unix)]
+    pub(crate) fn answer(&mut self, method: &str, params: &serde_json::Value) -> Result<serde_json::Value, rpc::Error> {
+        match method {
+            "lists" => Ok(serde_json::json!(self.doc.lists.iter().map(cli::list_summary).collect::<Vec<_>>())),
+            "show" => {
+                let list = &self.doc.lists[self.rpc_list(params)?];
+                Ok(serde_json::json!({ "id": list.id, "title": list.title, "tasks": list.list }))
+            }
+            "new_list" => {
+                let error = self.error.take();
+                self.update(Message::AddList(rpc_title(params)?));
+                if let Some(e) = std::mem::replace(&mut self.error, error) {
+                    return Err(rpc::Error::new(rpc::FAILED, e));
+                }
+                let list = self.doc.lists.last().ok_or(rpc::Error::new(rpc::FAILED, "the list was not added"))?;
+                Ok(cli::list_summary(list))
+            }
+            "remove_list" => {
+                let list = &self.doc.lists[self.rpc_list(params)?];
+                let summary = cli::list_summary(list);
+                self.update(Message::RemoveList(list.id));
+                Ok(summary)
+            }
+            "add" => {
+                let list = self.doc.lists[self.rpc_list(params)?].id;
+                self.rpc_update(list, Message::AddTask(rpc_title(params)?))?;
+                let id = self.doc.list(list).and_then(|l| l.list.last()).map(|t| t.id).ok_or(core::Error::ListNotFound(list))?;
+                self.task_reply(list, id)
+            }
+            "rename" => {
+                let (list, id) = self.rpc_task(params)?;
+                self.rpc_update(list, Message::RenameTask(id, rpc_title(params)?))?;
+                self.task_reply(list, id)
+            }
+            "advance" => {
+                let (list, id) = self.rpc_task(params)?;
+                self.rpc_update(list, Message::Forward(id))?;
+                self.task_reply(list, id)
+            }
+            "status" => {
+                let (list, id) = self.rpc_task(params)?;
+                let status: Status = rpc::typed_param(params, "status")?;
+                let current = self.doc.list(list).and_then(|l| l.task(id)).ok_or(core::Error::TaskNotFound(id))?.status;
+                if status == current.next() {
+                    self.rpc_update(list, Message::Forward(id))?;
+                } else if status != current {
+                    return Err(rpc::Error::new(rpc::INVALID_PARAMS, format!(
+                        "a {current:?} task can only move to {:?}, not {status:?}",
+                        current.next(),
+                    )));
+                }
+                self.task_reply(list, id)
+            }
+            "priority" => {
+                let (list, id) = self.rpc_task(params)?;
+                let priority = rpc::typed_param(params, "priority")?;
+                self.rpc_update(list, Message::SetPriority(id, priority))?;
+                self.task_reply(list, id)
+            }
+            "remove" => {
+                let (list, id) = self.rpc_task(params)?;
+                let reply = self.task_reply(list, id)?;
+                self.rpc_update(list, Message::Remove(id))?;
+                Ok(reply)
+            }
+            _ => Err(rpc::Error::new(rpc::METHOD_NOT_FOUND, format!("unknown method {method:?}"))),
+        }
+    }
+
+    /// Sends `msg` to `list` like a click would, returning the error a
+    /// rejected edit would have shown without showing it.
+    #[cfg(unix)]
+    fn rpc_update(&mut self, list: ListId, msg: Message) -> Result<(), rpc::Error> {
+        let shown = self.list_editor.error.take();
+        self.update(Message::InList(list, Box::new(msg)));
+        match std::mem::replace(&mut self.list_editor.error, shown) {
+            Some(e) => Err(rpc::Error::new(rpc::FAILED, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// The list named by the `list` parameter: a number, title or id prefix,
+    /// as on the command line.
+    #[cfg(unix)]
+    fn rpc_list(&self, params: &serde_json::Value) -> Result<usize, rpc::Error> {
+        cli::find_list(&self.doc, &rpc::param(params, "list")?).map_err(|e| rpc::Error::new(rpc::FAILED, e))
+    }
+
+    #[cfg(unix)]
+    fn rpc_task(&self, params: &serde_json::Value) -> Result<(ListId, TaskId), rpc::Error> {
+        let list = &self.doc.lists[self.rpc_list(params)?];
+        let index = cli::find_task(list, &rpc::param(params, "task")?).map_err(|e| rpc::Error::new(rpc::FAILED, e))?;
+        Ok((list.id, list.list[index].id))
+    }
+
+    /// A task with its list's id and its position in that list.
+    #[cfg(unix)]
+    fn task_reply(&self, list: ListId, id: TaskId) -> Result<serde_json::Value, rpc::Error> {
+        let tasks = self.doc.list(list).ok_or(core::Error::ListNotFound(list))?;
+        let index = tasks.list.iter().position(|t| t.id == id).ok_or(core::Error::TaskNotFound(id))?;
+        Ok(serde_json::json!({ "list": list, "index": index, "task": tasks.list[index] }))
+    }
+
+    /// A window on `doc` with nothing selected or being typed.
+    pub fn new(mut doc: List) -> Self {
+        doc.record_changes();
+        Self {
+            doc,
+            selected: None,
+            list_editor: ListEditor::default(),
+            adding_after: None,
+            new_title: String::new(),
+            editing: None,
+            error: None,
+            trash_open: false,
+            recovery: None,
+            last_change: None,
+            saving: false,
+            revision: 0,
+            written: Arc::default(),
+            backend: None,
+            unsaved_tasks: None,
+            backups: None,
+            backup_preview: None,
+            workspace: String::from(config::DEFAULT_WORKSPACE),
+            workspaces: Vec::new(),
+            new_workspace: None,
+            workspace_error: None,
+            on_disk: None,
+            disk_modified: None,
+            conflict: None,
+            instance_lock: None,
+            read_only: false,
+            other_instance: None,
+            undo: UndoHistory::default(),
+            toast: None,
+            server: None,
+        }
+    }
+
+    fn new_with_themes(themes: Vec<Themes>) -> Self {
+        Self::new(List { themes, ..List::new() })
+    }
+
+    fn load() -> Self {
+        match config::migrate_legacy_data() {
+            Ok(Some(dir)) => log_error(&format!("Moved data from ~/Tasks to {}", dir.display())),
+            Ok(None) => {}
+            Err(e) => log_error(&format!("Failed to move data from ~/Tasks: {e}")),
+        }
+        Self::open_workspace(&config::active_workspace())
+    }
+
+    fn open_workspace(workspace: &str) -> Self {
+        let Some(path) = core::data_path(workspace) else {
+            log_error("Could not resolve home directory to load lists.");
+            return Self::new_with_themes(Self::themes());
+        };
+        if let Err(e) = Self::ensure_parent_dir(&path) {
+            log_error(&format!("Failed to prepare data directory {}: {e}", path.display()));
+        }
+        // Lock before reading so a second window never quarantines or
+        // migrates a file the first one is using.
+        let lock = storage::lock(&path);
+        let mut app = Self {
+            workspace: workspace.to_string(),
+            workspaces: config::workspaces(),
+            ..Self::read_workspace(workspace, &path, lock)
+        };
+        if app.recovery.is_none() {
+            app.on_disk = Self::disk_state(&app.doc.lists, &app.doc.trash, app.doc.selected_theme);
+            app.disk_modified = Self::modified(&path);
+        }
+        if config::keep_undo_history()
+            && let Some(kept) = Self::read_undo_history(&path)
+            && kept.document == Self::fingerprint(&app.doc) {
+            app.undo = kept;
+        }
+        if !app.read_only && app.doc.purge_trash(config::trash_days()) {
+            app.mark_dirty();
+        }
+        // Purging on open is housekeeping, not a step to undo.
+        app.doc.take_changes();
+        app
+    }
+
+    /// Identifies the saved content of `doc`, to match an undo history
+    /// with the file it was saved next to.
+    fn fingerprint(doc: &List) -> u64 {
+        let mut hasher = std::hash::DefaultHasher::new();
+        if let Some(state) = Self::disk_state(&doc.lists, &doc.trash, doc.selected_theme) {
+            state.to_string().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn undo_history_path(path: &Path) -> PathBuf {
+        path.with_file_name("undo.json")
+    }
+
+    fn read_undo_history(path: &Path) -> Option<UndoHistory> {
+        let data = fs::read_to_string(Self::undo_history_path(path)).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// Writes the undo history next to the data file if the settings ask
+    /// for it to be kept across restarts.
+    fn save_undo_history(&mut self) {
+        if self.read_only || !config::keep_undo_history() {
+            return;
+        }
+        let Some(path) = core::data_path(&self.workspace) else {
+            return;
+        };
+        self.undo.document = Self::fingerprint(&self.doc);
+        let result = serde_json::to_vec(&self.undo)
+            .map_err(io::Error::other)
+            .and_then(|data| storage::write_atomic(&Self::undo_history_path(&path), &data));
+        if let Err(e) = result {
+            log_error(&format!("Failed to save undo history: {e}"));
+        }
+    }
+
+    /// Applies the changes of an undo or redo step, last first, and returns
+    /// the step that reverses it.
+    fn revert(&mut self, changes: Vec<Change>) -> Vec<Change> {
+        let mut reverse = Vec::new();
+        for change in changes.into_iter().rev() {
+            match self.doc.apply_change(change) {
+                Ok(change) => reverse.push(change),
+                Err(e) => log_error(&format!("Failed to undo a change: {e}")),
+            }
+        }
+        self.selected = self.selected.filter(|id| self.doc.lists.iter().any(|l| l.id == *id));
+        self.editing = None;
+        self.adding_after = None;
+        self.toast = None;
+        self.undo.coalescing = None;
+        self.unsaved_tasks = None;
+        self.prune_tag_filter();
+        self.reload_notes();
+        self.mark_dirty();
+        reverse
+    }
+
+    /// Reloads the open notes from the document after an undo or a change
+    /// from disk, so the next keystroke does not write the old text back.
+    /// Closes them if their task is gone.
+    fn reload_notes(&mut self) {
+        let notes = self.list_editor.notes_open
+            .and_then(|id| self.doc.list(self.selected?)?.task(id))
+            .map(|task| task.notes.clone().unwrap_or_default());
+        if notes.is_none() {
+            self.list_editor.notes_open = None;
+        }
+        #[cfg(feature = "gui")]
+        if let Some(notes) = notes {
+            self.list_editor.load_notes(&notes);
+        }
+    }
+
+    /// Drops filter tags that no task in the selected list carries any more,
+    /// so a stale filter cannot hide every task with no button to clear it.
+    fn prune_tag_filter(&mut self) {
+        if self.list_editor.tag_filter.is_empty() {
+            return;
+        }
+        let tags = self.selected.and_then(|id| self.doc.list(id)).map(Tasks::tags).unwrap_or_default();
+        self.list_editor.tag_filter.retain(|tag| tags.contains(&tag.as_str()));
+    }
+
+    fn themes() -> Vec<Themes> {
+        vec![
+            Themes::Default,
+            Themes::Dark,
+            Themes::Light,
+            Themes::SolarizedDark,
+            Themes::SolarizedLight,
+            Themes::GruvboxDark,
+            Themes::GruvboxLight,
+            Themes::KanagawaWave,
+            Themes::KanagawaDragon,
+            Themes::KanagawaLotus,
+            Themes::TokyoNight,
+            Themes::TokyoNightLight,
+            Themes::TokyoNightStorm,
+            Themes::Moonfly,
+            Themes::Nightfly,
+            Themes::Nord,
+            Themes::Ferra,
+            Themes::Dracula,
+            Themes::Oxocarbon,
+        ]
+    }
+
+    /// Loads the workspace at `path`. While another instance holds `lock`
+    /// the file is only read: nothing is quarantined, migrated or saved.
+    fn read_workspace(workspace: &str, path: &Path, lock: Result<fs::File, storage::LockError>) -> Self {
+        let mut app = Self::read_file(workspace, path, matches!(lock, Err(storage::LockError::Held(_))));
+        match lock {
+            Ok(lock) => app.instance_lock = Some(lock),
+            Err(storage::LockError::Held(pid)) => app.other_instance = pid,
+            Err(storage::LockError::Io(e)) => {
+                log_error(&format!("Failed to lock {}, continuing without: {e}", path.display()));
+            }
+        }
+        app
+    }
+
+    fn read_file(workspace: &str, path: &Path, held: bool) -> Self {
+        let themes = Self::themes();
+        let opened = if held { storage::open_read_only(path) } else { storage::open(path) };
+        let stored = opened.and_then(|backend| {
+            let doc = backend.load()?;
+            Ok((Arc::<dyn storage::Backend>::from(backend), doc))
+        });
+
+        let backend = match stored {
+            Err(e) => {
+                log_error(&format!("Failed to read lists file {}: {e}", path.display()));
+                return if held { Self::read_only(themes) } else { Self::recover(path, themes) };
+            }
+            Ok((backend, Some(doc))) => {
+                let outdated = migrations::is_outdated(&doc);
+                return match List::from_document(doc) {
+                    Ok(doc) => {
+                        let mut app = Self {
+                            backend: Some(backend),
+                            read_only: held,
+                            ..Self::new(List { themes, ..doc })
+                        };
+                        // Keep the ids the migration gave out.
+                        if outdated {
+                            app.save();
+                        }
+                        app
+                    }
+                    Err(e) => {
+                        log_error(&format!("Failed to parse lists file {}: {e}", path.display()));
+                        drop(backend);
+                        if held { Self::read_only(themes) } else { Self::recover(path, themes) }
+                    }
+                };
+            }
+            Ok((backend, None)) => backend,
+        };
+        if held {
+            // The instance holding the lock migrates todo.json itself.
+            return Self { backend: Some(backend), ..Self::read_only(themes) };
+        }
+        {
+            let old = path.with_file_name("todo.json");
+
+            if old.exists() {
+                let mut data = String::new();
+                if let Ok(mut f) = fs::File::open(&old) {
+                    let _ = f.read_to_string(&mut data);
+                    if let Ok(legacy) = Self::parse(&data) {
+                        let mut app = Self {
+                            workspace: workspace.to_string(),
+                            backend: Some(backend),
+                            ..Self::new(List { lists: legacy.lists, themes: themes.clone(), ..List::new() })
+                        };
+                        app.save();
+                        return app;
+                    }
+                }
+            }
+
+            Self { backend: Some(backend), ..Self::new_with_themes(themes) }
+        }
+    }
+
+    /// An empty document that is never saved, shown while another instance
+    /// holds a file this one could not read.
+    fn read_only(themes: Vec<Themes>) -> Self {
+        Self { read_only: true, ..Self::new_with_themes(themes) }
+    }
+
+    /// Starts with an empty document after `path` failed to load, moving the
+    /// unreadable file aside and holding on to the newest readable backup so
+    /// the user can restore it from the banner.
+    fn recover(path: &Path, themes: Vec<Themes>) -> Self {
+        let quarantined = match storage::quarantine(path) {
+            Ok(target) => {
+                log_error(&format!("Moved unreadable lists file to {}", target.display()));
+                Some(target)
+            }
+            Err(e) => {
+                log_error(&format!("Failed to move unreadable lists file {}: {e}", path.display()));
+                None
+            }
+        };
+        let backup = Self::backup_candidates(path)
+            .iter()
+            .find_map(|candidate| Self::read_lists(candidate));
+
+        Self {
+            recovery: Some(Recovery { quarantined, backup }),
+            backend: Self::open_backend(path),
+            ..Self::new_with_themes(themes)
+        }
+    }
+
+    /// `lists.json.bak` and every snapshot, most recently written first.
+    fn backup_candidates(path: &Path) -> Vec<PathBuf> {
+        let mut candidates: Vec<PathBuf> = storage::snapshots(&storage::backups_dir(path))
+            .into_iter()
+            .map(|s| s.path)
+            .collect();
+        candidates.push(storage::backup_path(path));
+        candidates.sort_by_key(|p| std::cmp::Reverse(fs::metadata(p).and_then(|m| m.modified()).ok()));
+        candidates
+    }
+
+    fn read_lists(path: &Path) -> Option<Vec<Tasks>> {
+        Self::read_document(path).map(|doc| doc.lists)
+    }
+
+    /// Reads a backup or snapshot without changing it.
+    fn read_document(path: &Path) -> Option<List> {
+        let doc = storage::open_read_only(path).and_then(|backend| backend.load()).ok()??;
+        List::from_document(doc).ok()
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// The persisted content that outside edits are compared against.
+    fn disk_state(lists: &[Tasks], trash: &[TrashItem], theme: Option<Themes>) -> Option<serde_json::Value> {
+        serde_json::to_value((lists, trash, theme)).ok()
+    }
+
+    /// Reloads the data file if something other than this app changed it.
+    /// With unsaved edits pending, asks the user instead of picking a side.
+    fn check_disk(&mut self) {
+        if self.saving || self.conflict.is_some() || self.recovery.is_some() {
+            return;
+        }
+        let Some(path) = core::data_path(&self.workspace) else {
+            return;
+        };
+        let modified = Self::modified(&path);
+        if modified.is_none() || modified == self.disk_modified {
+            return;
+        }
+        self.disk_modified = modified;
+        let theirs = self.backend.as_ref().and_then(|backend| backend.load().ok()?);
+        let Some(theirs) = theirs.and_then(|doc| List::from_document(doc).ok()) else {
+            log_error(&format!("Ignoring unreadable change to {}", path.display()));
+            return;
+        };
+        let state = Self::disk_state(&theirs.lists, &theirs.trash, theirs.selected_theme);
+        if state == self.on_disk {
+            return;
+        }
+        let conflict = Conflict { lists: theirs.lists, trash: theirs.trash, selected_theme: theirs.selected_theme };
+        if self.last_change.is_some() && !self.read_only {
+            self.conflict = Some(conflict);
+        } else {
+            self.take_theirs(conflict);
+        }
+    }
+
+    fn take_theirs(&mut self, theirs: Conflict) {
+        self.doc.replace_content(theirs.lists, theirs.trash, theirs.selected_theme);
+        self.selected = self.selected.filter(|id| self.doc.lists.iter().any(|l| l.id == *id));
+        self.editing = None;
+        self.adding_after = None;
+        self.last_change = None;
+        self.on_disk = Self::disk_state(&self.doc.lists, &self.doc.trash, self.doc.selected_theme);
+        self.reload_notes();
+    }
+
+    /// Parses a JSON data file of any known schema version.
+    fn parse(data: &str) -> Result<List, String> {
+        List::from_document(serde_json::from_str(data).map_err(|e| e.to_string())?)
+    }
+
+    fn load_backups(&self) -> Vec<BackupEntry> {
+        let Some(path) = core::data_path(&self.workspace) else {
+            return Vec::new();
+        };
+        storage::snapshots(&storage::backups_dir(&path))
+            .into_iter()
+            .map(|snapshot| BackupEntry {
+                counts: Self::read_lists(&snapshot.path)
+                    .map(|lists| (lists.len(), lists.iter().map(|l| l.list.len()).sum())),
+                path: snapshot.path,
+                taken_at: snapshot.taken_at,
+            })
+            .collect()
+    }
+
+    /// Applies one message from any frontend, recording it for undo.
+    pub fn update(&mut self, msg: Message) {
+        match msg {
+            Message::Undo => {
+                if let Some(changes) = self.undo.undo.pop_back() {
+                    let redo = self.revert(changes);
+                    self.undo.redo.push(redo);
+                }
+                return;
+            }
+            Message::Redo => {
+                if let Some(changes) = self.undo.redo.pop() {
+                    let undo = self.revert(changes);
+                    self.undo.undo.push_back(undo);
+                }
+                return;
+            }
+            Message::DismissToast => {
+                self.toast = None;
+                return;
+            }
+            _ => {}
+        }
+
+        let notice = msg.deletion_notice();
+        // Folding subtasks away is saved but is not an edit to step back through.
+        let undoable = !matches!(msg, Message::ToggleCollapsed(_));
+        self.apply(msg);
+        self.prune_tag_filter();
+        let changes = self.doc.take_changes();
+        self.note_unsaved(&changes);
+        if undoable && self.undo.record(changes, &self.doc) {
+            self.toast = notice.map(|notice| (notice.to_string(), Instant::now()));
+        }
+    }
+
+    fn apply(&mut self, msg: Message) {
+        match msg {
+            Message::AddListAfter(index) => {
+                self.adding_after = Some(index);
+                self.new_title.clear();
+                self.error = None;
+            }
+            Message::UpdateListTitle(title) => {
+                self.new_title = title;
+            }
+            Message::AddList(title) => {
+                match self.doc.add_list(&title) {
+                    Ok(_) => self.mark_dirty(),
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+            Message::ConfirmAddList => {
+                let index = self.adding_after.map_or(self.doc.lists.len(), |i| i + 1);
+                match self.doc.insert_list(index, &self.new_title) {
+                    Ok(_) => {
+                        self.new_title.clear();
+                        self.adding_after = None;
+                        self.error = None;
+                        self.mark_dirty();
+                    }
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+            Message::CancelAddList => {
+                self.new_title.clear();
+                self.adding_after = None;
+                self.error = None;
+            }
+            Message::RemoveList(id) => {
+                if self.doc.remove_list(id).is_ok() {
+                    self.mark_dirty();
+                }
+            }
+            Message::ChangeListTitle(id) => {
+                if let Some(list) = self.doc.list(id) {
+                    self.new_title = list.title.clone();
+                    self.editing = Some(id);
+                    self.error = None;
+                }
+            }
+            Message::ConfirmListEdit => {
+                if let Some(id) = self.editing {
+                    match self.doc.rename_list(id, &self.new_title) {
+                        Ok(()) => {
+                            self.editing = None;
+                            self.new_title.clear();
+                            self.error = None;
+                            self.mark_dirty();
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+            }
+            Message::CancelListEdit => {
+                self.editing = None;
+                self.new_title.clear();
+                self.error = None;
+            }
+            Message::SelectList(id) => {
+                if self.doc.list(id).is_some() {
+                    if self.selected != Some(id) {
+                        self.list_editor = ListEditor::default();
+                    }
+                    self.selected = Some(id);
+                }
+            }
+            Message::BackToLists => {
+                self.selected = None;
+                self.adding_after = None;
+                self.new_title.clear();
+                self.editing = None;
+                self.error = None;
+            }
+
+            Message::ThemeChanged(theme) => {
+                self.doc.set_theme(theme);
+                self.mark_dirty();
+            }
+
+            Message::RestoreBackup => {
+                if let Some(lists) = self.recovery.take().and_then(|r| r.backup) {
+                    self.doc.replace_lists(lists);
+                    self.selected = None;
+                    self.editing = None;
+                    self.mark_dirty();
+                }
+            }
+            Message::DismissRecovery => {
+                if self.recovery.as_ref().is_some_and(|r| r.quarantined.is_some()) {
+                    self.recovery = None;
+                }
+            }
+
+            Message::ShowBackups => {
+                self.backups = Some(self.load_backups());
+                self.backup_preview = None;
+            }
+            Message::CloseBackups => {
+                self.backups = None;
+                self.backup_preview = None;
+            }
+            Message::PreviewBackup(path) => {
+                match Self::read_lists(&path) {
+                    Some(lists) => self.backup_preview = Some((path, lists)),
+                    None => log_error(&format!("Failed to read backup {}", path.display())),
+                }
+            }
+            Message::RestoreSnapshot(path) => {
+                match Self::read_lists(&path) {
+                    Some(lists) => {
+                        self.doc.replace_lists(lists);
+                        self.selected = None;
+                        self.editing = None;
+                        self.backups = None;
+                        self.backup_preview = None;
+                        self.mark_dirty();
+                    }
+                    None => log_error(&format!("Failed to read backup {}", path.display())),
+                }
+            }
+
+            Message::SwitchWorkspace(name) => {
+                if name != self.workspace {
+                    self.switch_workspace(&name);
+                }
+            }
+            Message::AddWorkspace => {
+                self.selected = None;
+                self.new_workspace = Some(String::new());
+            }
+            Message::UpdateWorkspaceName(name) => {
+                self.new_workspace = Some(name);
+                self.workspace_error = None;
+            }
+            Message::ConfirmAddWorkspace => {
+                if let Some(name) = self.new_workspace.as_deref() {
+                    let name = name.trim().to_string();
+                    if !config::is_valid_workspace(&name) {
+                        self.workspace_error = Some(String::from(
+                            "Workspace names must not be empty, \".\" or \"..\", or contain / or \\.",
+                        ));
+                    } else {
+                        self.new_workspace = None;
+                        self.workspace_error = None;
+                        if name != self.workspace {
+                            self.switch_workspace(&name);
+                        }
+                    }
+                }
+            }
+            Message::CancelAddWorkspace => {
+                self.new_workspace = None;
+                self.workspace_error = None;
+            }
+
+            Message::StartServer => {
+                if self.server.is_none() && !self.read_only {
+                    self.server = Some(SocketAddr::from((Ipv4Addr::LOCALHOST, server::DEFAULT_PORT)));
+                }
+            }
+            Message::Http(request) => {
+                if request.answer(&mut self.doc, !self.read_only) {
+                    self.mark_dirty();
+                }
+            }
+            Message::ServerFailed(e) => {
+                self.server = None;
+                log_error(&format!("Failed to start the HTTP API: {e}"));
+            }
+
+            #[cfg(unix)]
+            Message::Rpc(request) => {
+                let result = self.answer(&request.method, &request.params);
+                request.respond(result);
+            }
+
+            Message::CheckDisk => self.check_disk(),
+            Message::RetryLock => {
+                if self.read_only {
+                    let workspace = self.workspace.clone();
+                    self.switch_workspace(&workspace);
+                }
+            }
+            Message::KeepMine => {
+                if let Some(theirs) = self.conflict.take() {
+                    self.on_disk = Self::disk_state(&theirs.lists, &theirs.trash, theirs.selected_theme);
+                    self.unsaved_tasks = None;
+                    self.mark_dirty();
+                }
+            }
+            Message::TakeTheirs => {
+                if let Some(theirs) = self.conflict.take() {
+                    self.take_theirs(theirs);
+                }
+            }
+            Message::MergeChanges => {
+                if let Some(theirs) = self.conflict.take() {
+                    let base = self.on_disk.take()
+                        .and_then(|state| serde_json::from_value(state).ok())
+                        .map(|(lists, trash, selected_theme)| List { lists, trash, selected_theme, ..List::new() })
+                        .unwrap_or_default();
+                    self.on_disk = Self::disk_state(&theirs.lists, &theirs.trash, theirs.selected_theme);
+                    let theirs = List { lists: theirs.lists, trash: theirs.trash, selected_theme: theirs.selected_theme, ..List::new() };
+                    self.doc.merge(&base, theirs);
+                    self.reload_notes();
+                    self.unsaved_tasks = None;
+                    self.mark_dirty();
+                }
+            }
+
+            Message::ShowTrash => {
+                if self.doc.purge_trash(config::trash_days()) {
+                    self.mark_dirty();
+                }
+                self.trash_open = true;
+            }
+            Message::CloseTrash => {
+                self.trash_open = false;
+            }
+            Message::RestoreFromTrash(id) => {
+                if self.doc.restore_from_trash(id).is_ok() {
+                    self.mark_dirty();
+                }
+            }
+            Message::DeleteForever(id) => {
+                if self.doc.delete_from_trash(id).is_ok() {
+                    self.mark_dirty();
+                }
+            }
+            Message::EmptyTrash => {
+                if self.doc.empty_trash() {
+                    self.mark_dirty();
+                }
+            }
+
+            Message::Tick => {
+                if self.toast.as_ref().is_some_and(|(_, at)| at.elapsed() >= TOAST_DURATION) {
+                    self.toast = None;
+                }
+            }
+            Message::Saved(result) => {
+                self.saving = false;
+                if let Err(e) = result {
+                    self.unsaved_tasks = None;
+                    log_error(&e);
+                }
+            }
+            Message::InList(list, msg) => self.update_list(list, *msg),
+            msg => {
+                if let Some(list) = self.selected {
+                    self.update_list(list, msg);
+                }
+            }
+        }
+    }
+
+    /// Flushes pending changes and replaces the document with `name`'s,
+    /// creating the workspace if it does not exist yet.
+    fn switch_workspace(&mut self, name: &str) {
+        self.close();
+        let mut next = Self::open_workspace(name);
+        if !next.workspaces.iter().any(|w| w == name) {
+            next.workspaces.push(name.to_string());
+            next.mark_dirty();
+        }
+        next.saving = self.saving;
+        next.server = self.server.filter(|_| !next.read_only);
+        *self = next;
+        if let Err(e) = config::remember_workspace(name) {
+            log_error(&format!("Failed to remember workspace {name}: {e}"));
+        }
+    }
+
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn undo_steps_back_and_forth_and_joins_typing_in_notes() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        app.doc.take_changes();
+        app.update(Message::SelectList(list_id));
+        app.update(Message::UpdateNewTitle(String::from("Report")));
+        app.update(Message::ConfirmAdd);
+        let id = app.doc.lists[0].list[0].id;
+        app.update(Message::SetPriority(id, Priority::High));
+        app.update(Message::OpenNotes(id));
+        for notes in ["D", "Dr", "Draft", "Draft é"] {
+            app.update(Message::NotesChanged(String::from(notes)));
+        }
+        assert_eq!(app.undo.undo.len(), 3);
+
+        let task = |app: &App| app.doc.lists[0].list.first().cloned();
+        app.update(Message::Undo);
+        assert_eq!(task(&app).unwrap().notes, None);
+        app.update(Message::Undo);
+        assert_eq!(task(&app).unwrap().priority, Priority::None);
+        app.update(Message::Undo);
+        assert!(task(&app).is_none());
+        app.update(Message::Undo);
+
+        app.update(Message::Redo);
+        app.update(Message::Redo);
+        app.update(Message::Redo);
+        let task = task(&app).unwrap();
+        assert_eq!((task.id, task.priority), (id, Priority::High));
+        assert_eq!(task.notes.as_deref(), Some("Draft é"));
+
+        app.update(Message::Undo);
+        app.update(Message::SortChanged(SortOrder::Priority));
+        assert!(app.undo.redo.is_empty());
+    }
+
+    #[test]
+    fn undo_skips_steps_that_change_nothing_and_reloads_open_notes() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        let id = app.doc.add_task(list_id, "Report").unwrap();
+        app.doc.add_subtask(list_id, id, "Outline").unwrap();
+        app.doc.take_changes();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::ToggleCollapsed(id));
+        app.update(Message::ChangeTitle(id));
+        app.update(Message::ConfirmEdit);
+        app.update(Message::SetPriority(id, Priority::None));
+        assert!(app.undo.undo.is_empty());
+        assert!(app.doc.lists[0].list[0].collapsed);
+
+        app.update(Message::OpenNotes(id));
+        app.update(Message::NotesChanged(String::from("Draft")));
+        #[cfg(feature = "gui")]
+        app.list_editor.load_notes("Draft");
+        app.update(Message::Undo);
+        assert_eq!(app.doc.lists[0].list[0].notes, None);
+        assert_eq!(app.list_editor.notes_open, Some(id));
+        #[cfg(feature = "gui")]
+        assert_eq!(app.list_editor.notes_editor.text().trim_end(), "");
+    }
+
+    #[test]
+    fn undo_puts_a_deleted_task_back_and_takes_it_out_of_the_trash() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        let ids: Vec<TaskId> = ["A", "B", "C"].iter().map(|t| app.doc.add_task(list_id, t).unwrap()).collect();
+        app.doc.take_changes();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::Remove(ids[1]));
+        assert_eq!(app.doc.trash.len(), 1);
+        assert!(app.toast.is_some());
+        app.update(Message::Undo);
+
+        assert!(app.doc.trash.is_empty());
+        let order: Vec<TaskId> = app.doc.lists[0].list.iter().map(|t| t.id).collect();
+        assert_eq!(order, ids);
+    }
+
+    #[test]
+    fn restoring_a_task_brings_back_its_deleted_list() {
+        let mut app = App::new(List::new());
+        let milk = Task::new(String::from("Milk"));
+        let eggs = Task::new(String::from("Eggs"));
+        let (milk_id, eggs_id) = (milk.id, eggs.id);
+        app.doc.lists.push(Tasks { title: String::from("Groceries"), list: vec![milk, eggs], ..Tasks::default() });
+        let list_id = app.doc.lists[0].id;
+
+        app.selected = Some(list_id);
+        app.update(Message::Remove(milk_id));
+        app.selected = None;
+        app.update(Message::RemoveList(list_id));
+        assert!(app.doc.lists.is_empty());
+        assert_eq!(app.doc.trash.len(), 2);
+
+        app.update(Message::RestoreFromTrash(milk_id));
+        assert!(app.doc.trash.is_empty());
+        let ids: Vec<TaskId> = app.doc.lists[0].list.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [milk_id, eggs_id]);
+    }
+
+    #[test]
+    fn a_rejected_title_keeps_the_input_and_shows_why() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::AddAfter(0));
+        app.update(Message::UpdateNewTitle(String::from("   ")));
+        app.update(Message::ConfirmAdd);
+        assert!(app.doc.lists[0].list.is_empty());
+        assert_eq!(app.list_editor.new_title, "   ");
+        assert!(app.list_editor.error.is_some());
+        assert_eq!(app.revision, 0);
+
+        app.update(Message::UpdateNewTitle(String::from("Report")));
+        app.update(Message::ConfirmAdd);
+        assert_eq!(app.doc.lists[0].list.len(), 1);
+        assert!(app.list_editor.new_title.is_empty());
+        assert!(app.list_editor.error.is_none());
+    }
+
+    #[test]
+    fn editing_starts_from_the_task_and_keeps_its_date_if_the_new_one_is_invalid() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        let id = app.doc.add_task(list_id, "Report").unwrap();
+        let due = parse_due_date("2024-12-31");
+        app.doc.set_due(list_id, id, due, None).unwrap();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::ChangeTitle(id));
+        assert_eq!(app.list_editor.new_title, "Report");
+        assert_eq!(app.list_editor.new_due_date, "2024-12-31");
+
+        app.update(Message::UpdateNewTitle(String::from("Final report")));
+        app.update(Message::UpdateNewDueDate(String::from("next friday")));
+        app.update(Message::ConfirmEdit);
+        let task = &app.doc.lists[0].list[0];
+        assert_eq!(task.title, "Report");
+        assert_eq!(task.due_date, due);
+        assert_eq!(app.list_editor.editing, Some(id));
+        assert!(app.list_editor.error.is_some());
+
+        app.update(Message::UpdateNewDueDate(String::new()));
+        app.update(Message::ConfirmEdit);
+        let task = &app.doc.lists[0].list[0];
+        assert_eq!(task.title, "Final report");
+        assert_eq!(task.due_date, None);
+        assert!(app.list_editor.error.is_none());
+    }
+
+    #[test]
+    fn an_unreadable_repeat_rule_keeps_the_old_one() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Chores").unwrap();
+        let id = app.doc.add_task(list_id, "Water plants").unwrap();
+        app.doc.set_recurrence(list_id, id, Some(Recurrence::Daily)).unwrap();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::ChangeTitle(id));
+        app.update(Message::UpdateNewRecurrence(String::from("fortnightly")));
+        app.update(Message::ConfirmEdit);
+        assert_eq!(app.doc.lists[0].list[0].recurrence, Some(Recurrence::Daily));
+        assert_eq!(app.list_editor.new_recurrence, "fortnightly");
+        assert!(app.list_editor.error.is_some());
+
+        app.update(Message::UpdateNewRecurrence(String::from("every 2 days")));
+        app.update(Message::ConfirmEdit);
+        assert_eq!(app.doc.lists[0].list[0].recurrence, Some(Recurrence::EveryNDays(2)));
+        assert!(app.list_editor.error.is_none());
+    }
+
+    #[test]
+    fn a_filter_tag_no_task_carries_is_dropped() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+        let id = app.doc.add_task(list_id, "Report").unwrap();
+        app.doc.set_tags(list_id, id, vec![String::from("q3")]).unwrap();
+        app.update(Message::SelectList(list_id));
+        app.update(Message::ToggleTagFilter(String::from("q3")));
+        assert_eq!(app.list_editor.tag_filter, ["q3"]);
+
+        app.update(Message::ChangeTitle(id));
+        app.update(Message::UpdateNewTags(String::from("q4")));
+        app.update(Message::ConfirmEdit);
+        assert!(app.list_editor.tag_filter.is_empty());
+
+        app.update(Message::Undo);
+        app.update(Message::ToggleTagFilter(String::from("q3")));
+        app.update(Message::Remove(id));
+        assert!(app.list_editor.tag_filter.is_empty());
+    }
+
+    #[test]
+    fn a_blank_list_title_is_reported_and_changes_nothing() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Work").unwrap();
+
+        app.update(Message::AddListAfter(0));
+        app.update(Message::UpdateListTitle(String::from(" ")));
+        app.update(Message::ConfirmAddList);
+        assert_eq!(app.doc.lists.len(), 1);
+        assert_eq!(app.adding_after, Some(0));
+        assert!(app.error.is_some());
+        app.update(Message::CancelAddList);
+        assert!(app.error.is_none());
+
+        app.update(Message::ChangeListTitle(list_id));
+        assert_eq!(app.new_title, "Work");
+        app.update(Message::UpdateListTitle(String::new()));
+        app.update(Message::ConfirmListEdit);
+        assert_eq!(app.doc.lists[0].title, "Work");
+        assert_eq!(app.editing, Some(list_id));
+        assert!(app.error.is_some());
+        assert_eq!(app.revision, 0);
+    }
+
+    #[test]
+    fn trash_actions_on_missing_items_change_nothing() {
+        let mut app = App::new(List::new());
+        let missing = Uuid::new_v4();
+
+        app.update(Message::RestoreFromTrash(missing));
+        app.update(Message::DeleteForever(missing));
+
+        assert_eq!(app.revision, 0);
+        assert!(app.last_change.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn control_socket_calls_edit_lists_by_title_and_report_positions() {
+        let mut app = App::new(List::new());
+        app.doc.add_list("Work").unwrap();
+        let params = |extra: serde_json::Value| {
+            let mut params = serde_json::json!({ "list": "work" });
+            params.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            params
+        };
+
+        let added = app.answer("add", &params(serde_json::json!({ "title": "Report" }))).unwrap();
+        assert_eq!(added["index"], 0);
+        assert_eq!(added["task"]["title"], "Report");
+        let advanced = app.answer("advance", &params(serde_json::json!({ "task": "Report" }))).unwrap();
+        assert_eq!(advanced["task"]["status"], "InProgress");
+        let done = app.answer("status", &params(serde_json::json!({ "task": 1, "status": "Complete" }))).unwrap();
+        assert_eq!(done["task"]["status"], "Complete");
+        let error = app.answer("status", &params(serde_json::json!({ "task": 1, "status": "Pending" }))).unwrap_err();
+        assert_eq!(error.code, rpc::INVALID_PARAMS);
+        assert_eq!(app.answer("rename", &params(serde_json::json!({ "task": 1, "title": " " }))).unwrap_err().code, rpc::INVALID_PARAMS);
+        assert_eq!(app.doc.lists[0].list[0].status, Status::Complete);
+        assert_eq!(app.undo.undo.len(), 3);
+        app.update(Message::Undo);
+        assert_eq!(app.doc.lists[0].list[0].status, Status::InProgress);
+        app.update(Message::Redo);
+        app.answer("remove", &params(serde_json::json!({ "task": 1 }))).unwrap();
+        assert!(app.doc.lists[0].list.is_empty());
+        assert_eq!(app.doc.trash.len(), 1);
+        assert_eq!(app.selected, None);
+
+        let error = app.answer("add", &serde_json::json!({ "list": "Home", "title": "Dishes" })).unwrap_err();
+        assert_eq!(error.code, rpc::FAILED);
+        assert_eq!(app.answer("fly", &serde_json::Value::Null).unwrap_err().code, rpc::METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn an_invalid_workspace_name_is_reported_in_place() {
+        let mut app = App::new(List::new());
+        app.update(Message::AddWorkspace);
+        app.update(Message::UpdateWorkspaceName(String::from("a/b")));
+        app.update(Message::ConfirmAddWorkspace);
+
+        assert_eq!(app.new_workspace.as_deref(), Some("a/b"));
+        assert!(app.workspace_error.is_some());
+        app.update(Message::UpdateWorkspaceName(String::from("ab")));
+        assert!(app.workspace_error.is_none());
+    }
+
+    #[test]
+    fn a_file_locked_elsewhere_is_only_read() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.json");
+        fs::write(&path, "not json").unwrap();
+
+        let app = App::read_workspace("default", &path, Err(storage::LockError::Held(Some(7))));
+
+        assert!(app.read_only);
+        assert_eq!(app.other_instance, Some(7));
+        assert!(app.recovery.is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not json");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_slow_save_never_overwrites_a_newer_one() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.json");
+        let written = Mutex::new(0);
+        let backend: Arc<dyn storage::Backend> = Arc::from(storage::open(&path).unwrap());
+        let snapshot = |title: &str| Snapshot {
+            backend: backend.clone(),
+            doc: serde_json::json!({ "lists": [], "themes": [], "selected_theme": title }),
+            tasks: None,
+            recovering: false,
+        };
+
+        App::write(snapshot("newer"), 2, &written).unwrap();
+        App::write(snapshot("older"), 1, &written).unwrap();
+
+        assert_eq!(*written.lock().unwrap(), 2);
+        assert!(fs::read_to_string(&path).unwrap().contains("newer"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn undo_history_is_bounded() {
+        let mut history = UndoHistory::default();
+        let doc = List::new();
+        for _ in 0..UNDO_LIMIT + 10 {
+            history.record(vec![Change::SetTheme { theme: None }], &doc);
+        }
+        assert_eq!(history.undo.len(), UNDO_LIMIT);
+    }
+
+    #[test]
+    fn removing_a_task_closes_its_notes() {
+        let mut app = App::new(List::new());
+        let list_id = app.doc.add_list("Trip").unwrap();
+        let id = app.doc.add_task(list_id, "Pack").unwrap();
+        app.update(Message::SelectList(list_id));
+
+        app.update(Message::OpenNotes(Uuid::new_v4()));
+        assert_eq!(app.list_editor.notes_open, None);
+        app.update(Message::OpenNotes(id));
+        app.update(Message::NotesChanged(String::from("passport")));
+        assert_eq!(app.doc.lists[0].list[0].notes.as_deref(), Some("passport"));
+        app.update(Message::Remove(id));
+        assert_eq!(app.list_editor.notes_open, None);
+    }
+}
@@ -0,0 +1,982 @@
+//! What the window draws, and the iced glue that feeds it messages.
+use super::*;
+use chrono::{DateTime, Local};
+use iced::{
+    self,
+    Alignment,
+    Element,
+    Fill,
+    widget::*,
+    widget::{
+        text::Wrapping,
+        column,
+        container,
+    },
+    FillPortion,
+    Subscription,
+    keyboard,
+    time,
+    window,
+};
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+const DISK_POLL: Duration = Duration::from_secs(1);
+
+/// Why an edit was rejected, shown under its input.
+fn error_line(error: Option<&str>) -> Option<Text<'_>> {
+    error.map(|e| text(e).size(14).style(text::danger))
+}
+
+fn tag_chip(tag: &str) -> Container<'_, Message> {
+    container(text(format!("#{tag}")).size(12).style(text::secondary))
+        .padding([2, 8])
+        .style(container::rounded_box)
+}
+
+impl TrashItem {
+    fn describe(&self) -> String {
+        match &self.item {
+            Trashed::Task { task, list_title, parent: None, .. } => format!("{} (from {list_title})", task.title),
+            Trashed::Task { task, list_title, parent: Some(_), .. } => {
+                format!("{} (subtask, from {list_title})", task.title)
+            }
+            Trashed::List { list, .. } => format!("List: {} ({} task(s))", list.title, list.list.len()),
+        }
+    }
+}
+
+impl ListEditor {
+    /// Replaces the text in the notes editor.
+    pub(super) fn load_notes(&mut self, notes: &str) {
+        self.notes_editor = text_editor::Content::with_text(notes);
+    }
+
+    fn input_row(&self, on_save: Message, on_cancel: Message) -> Column<'_, Message> {
+        let inputs = row![
+            text_input("New task title...", &self.new_title)
+                .on_input(Message::UpdateNewTitle)
+                .padding(8)
+                .width(Fill),
+            text_input("Due (YYYY-MM-DD)", &self.new_due_date)
+                .on_input(Message::UpdateNewDueDate)
+                .padding(8)
+                .width(160),
+            text_input("Time (HH:MM)", &self.new_due_time)
+                .on_input(Message::UpdateNewDueTime)
+                .padding(8)
+                .width(120),
+            text_input("#tags", &self.new_tags)
+                .on_input(Message::UpdateNewTags)
+                .padding(8)
+                .width(140),
+            text_input("Repeat (e.g. weekly mon)", &self.new_recurrence)
+                .on_input(Message::UpdateNewRecurrence)
+                .padding(8)
+                .width(140),
+            button("Save").on_press(on_save),
+            button("Cancel").style(button::danger).on_press(on_cancel),
+        ]
+        .spacing(8);
+        column![inputs].push_maybe(error_line(self.error.as_deref())).spacing(4)
+    }
+
+    pub fn view<'a>(&'a self, tasks: &'a Tasks) -> Element<'a, Message> {
+        let mut interface = column![]
+            .spacing(16)
+            .padding(16);
+
+        if !tasks.list.is_empty() {
+            interface = interface.push(
+                row![
+                    text("Sort by").style(text::secondary),
+                    pick_list(SortOrder::ALL, Some(tasks.sort_order), Message::SortChanged),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+
+        let tags = tasks.tags();
+        if !tags.is_empty() {
+            let mut filter = row![text("Filter").style(text::secondary)]
+                .spacing(8)
+                .align_y(Alignment::Center);
+            for tag in tags {
+                let selected = self.tag_filter.iter().any(|t| t == tag);
+                filter = filter.push(
+                    button(text(format!("#{tag}")).size(14))
+                        .style(if selected { button::primary } else { button::secondary })
+                        .on_press(Message::ToggleTagFilter(tag.to_string())),
+                );
+            }
+            if !self.tag_filter.is_empty() {
+                filter = filter.push(
+                    button(text("Clear").size(14))
+                        .style(button::text)
+                        .on_press(Message::ClearTagFilter),
+                );
+            }
+            interface = interface.push(filter.wrap());
+        }
+
+        if tasks.list.is_empty() {
+            if self.adding_after == Some(0) {
+                interface = interface.push(self.input_row(Message::ConfirmAdd, Message::CancelAdd));
+            } else {
+                interface = interface.push(button("Add Task")
+                    .style(button::secondary)
+                    .on_press(Message::AddAfter(0)));
+            }
+        }
+
+        for (position, i) in tasks.display_order(&self.tag_filter).into_iter().enumerate() {
+            let task = &tasks.list[i];
+            if position > 0 {
+                interface = interface.push(container(Rule::horizontal(1)).width(Fill));
+            }
+
+            interface = interface.push(container(task.view()).padding(8));
+
+            if !task.collapsed {
+                for subtask in &task.subtasks {
+                    interface = interface.push(container(subtask.subtask_view(task.id)).padding([0, 48]));
+                }
+            }
+
+            if self.adding_subtask == Some(task.id) {
+                interface = interface.push(
+                    column![
+                        row![
+                            text_input("New step title...", &self.new_title)
+                                .on_input(Message::UpdateNewTitle)
+                                .padding(8)
+                                .width(Fill),
+                            button("Save").on_press(Message::ConfirmAddSubtask),
+                            button("Cancel").style(button::danger).on_press(Message::CancelAdd),
+                        ]
+                        .spacing(8),
+                    ]
+                    .push_maybe(error_line(self.error.as_deref()))
+                    .spacing(4)
+                    .padding([0, 48]),
+                );
+            }
+
+            if self.history_open == Some(task.id) {
+                interface = interface.push(task.history_view());
+            }
+
+            if self.notes_open == Some(task.id) {
+                interface = interface.push(
+                    column![
+                        row![
+                            text("Notes").size(16).style(text::secondary),
+                            horizontal_space(),
+                            button("Close").style(button::secondary).on_press(Message::CloseNotes),
+                        ]
+                        .align_y(Alignment::Center),
+                        text_editor(&self.notes_editor)
+                            .placeholder("Add notes...")
+                            .on_action(Message::EditNotes)
+                            .height(160),
+                    ]
+                    .spacing(8)
+                    .padding([0, 8]),
+                );
+            }
+
+            if self.editing == Some(task.id) {
+                interface = interface.push(self.input_row(Message::ConfirmEdit, Message::CancelEdit))
+            }
+        }
+
+        if !tasks.list.is_empty() {
+            let end_index = tasks.list.len();
+            if self.adding_after == Some(end_index) {
+                interface = interface.push(
+                    self.input_row(Message::ConfirmAdd, Message::CancelAdd).padding(4),
+                );
+            } else {
+                interface = interface.push(button("Add Task")
+                    .style(button::secondary)
+                    .on_press(Message::AddAfter(end_index)));
+            }
+        }
+        let scrollable_list = scrollable(interface.spacing(12)).height(Fill);
+
+        scrollable_list.into()
+    }
+}
+
+impl Task {
+    fn priority_badge(&self) -> Option<Element<'_, Message>> {
+        let style = match self.priority {
+            Priority::None => return None,
+            Priority::Low => text::secondary,
+            Priority::Medium => text::primary,
+            Priority::High => text::success,
+            Priority::Urgent => text::danger,
+        };
+        Some(
+            container(text(self.priority.to_string()).size(12).style(style))
+                .padding([2, 8])
+                .style(container::bordered_box)
+                .into(),
+        )
+    }
+
+    fn due_label(&self) -> Option<String> {
+        let date = self.due_date?;
+        Some(match self.due_time {
+            Some(time) => format!("Due {} {}", date.format(DATE_FORMAT), time.format(TIME_FORMAT)),
+            None => format!("Due {}", date.format(DATE_FORMAT)),
+        })
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let id = self.id;
+        let collapse_toggle = (!self.subtasks.is_empty()).then(|| {
+            button(text(if self.collapsed { "\u{25B8}" } else { "\u{25BE}" }).size(16))
+                .style(button::text)
+                .on_press(Message::ToggleCollapsed(id))
+        });
+        let progress = (!self.subtasks.is_empty()).then(|| {
+            let (done, total) = self.progress();
+            text(format!("{done}/{total}")).size(16).style(text::secondary)
+        });
+
+        let mut interface = row![]
+            .push_maybe(collapse_toggle)
+            .push_maybe(self.priority_badge())
+            .push(text(&self.title).size(20).wrapping(Wrapping::Word).width(FillPortion(4)))
+            .push(
+                text(format!(" - {:?}", self.status))
+                    .wrapping(Wrapping::None)
+                    .size(16)
+                    .style(text::secondary),
+            )
+            .push_maybe(progress)
+            .push_maybe(self.recurrence.as_ref().map(|r| {
+                text(format!("\u{21BB} {r}")).size(14).style(text::secondary)
+            }))
+            .extend(self.tags.iter().map(|tag| tag_chip(tag).into()))
+            .push_maybe(self.notes.as_ref().map(|_| text("\u{270E}").size(16).style(text::secondary)))
+            .spacing(12)
+            .align_y(Alignment::Center);
+
+        if let Some(label) = self.due_label() {
+            let now = Local::now().naive_local();
+            let due = if self.is_overdue(now) {
+                text(format!("{label} (overdue)")).style(text::danger)
+            } else if self.is_due_today(now) {
+                text(format!("{label} (today)")).style(text::primary)
+            } else {
+                text(label).style(text::secondary)
+            };
+            interface = interface.push(due.size(14).wrapping(Wrapping::None));
+        }
+
+        interface = match self.status {
+            Status::Pending => interface.push(
+                container(
+                    button("Start")
+                        .style(button::success)
+                        .on_press(Message::Forward(id))
+                )
+            ),
+            Status::InProgress | Status::Complete => {
+                let checked = matches!(self.status, Status::Complete);
+                interface.push(
+                    checkbox("", checked)
+                        .style(checkbox::success)
+                        .on_toggle(move |_| Message::Forward(id))
+                )
+            }
+        };
+
+        interface = interface.push(
+            pick_list(Priority::ALL, Some(self.priority), move |p| Message::SetPriority(id, p))
+                .text_size(14)
+        );
+
+        interface = interface.push(
+            button("Step")
+                .style(button::secondary)
+                .on_press(Message::AddSubtask(id))
+        );
+
+        interface = interface.push(
+            button("Notes")
+                .style(button::secondary)
+                .on_press(Message::OpenNotes(id))
+        );
+
+        interface = interface.push(
+            button("History")
+                .style(button::secondary)
+                .on_press(Message::ShowHistory(id))
+        );
+
+        interface = interface.push(
+            button("Edit")
+                .style(button::secondary)
+                .on_press(Message::ChangeTitle(id))
+        );
+
+        interface = interface.push(
+            button("Remove")
+                .style(button::danger)
+                .on_press(Message::Remove(id))
+        );
+
+        container(interface).padding(4).width(Fill).into()
+    }
+
+    fn history_view(&self) -> Element<'_, Message> {
+        let stamp = |at: Option<DateTime<Local>>| {
+            at.map(|at| at.format(TIMESTAMP_FORMAT).to_string())
+                .unwrap_or_else(|| String::from("-"))
+        };
+
+        let mut interface = column![
+            row![
+                text("History").size(16).style(text::secondary),
+                horizontal_space(),
+                button("Close").style(button::secondary).on_press(Message::CloseHistory),
+            ]
+            .align_y(Alignment::Center),
+            text(format!("Created: {}", stamp(self.created_at))).size(14),
+            text(format!("Started: {}", stamp(self.started_at))).size(14),
+            text(format!("Completed: {}", stamp(self.completed_at))).size(14),
+        ]
+        .spacing(4)
+        .padding([0, 8]);
+
+        if self.history.is_empty() {
+            interface = interface.push(text("No status changes yet.").size(14).style(text::secondary));
+        }
+        for change in &self.history {
+            interface = interface.push(
+                text(format!(
+                    "{}  {:?} \u{2192} {:?}",
+                    change.at.format(TIMESTAMP_FORMAT),
+                    change.from,
+                    change.to
+                ))
+                .size(14)
+                .style(text::secondary),
+            );
+        }
+
+        interface.into()
+    }
+
+    fn subtask_view(&self, parent: TaskId) -> Element<'_, Message> {
+        let id = self.id;
+        let mut interface = row![
+            text(&self.title).size(16).wrapping(Wrapping::Word).width(Fill),
+            text(format!(" - {:?}", self.status))
+                .wrapping(Wrapping::None)
+                .size(14)
+                .style(text::secondary),
+        ]
+        .spacing(12)
+        .align_y(Alignment::Center);
+
+        interface = match self.status {
+            Status::Pending => interface.push(
+                button(text("Start").size(14))
+                    .style(button::success)
+                    .on_press(Message::ForwardSubtask(parent, id))
+            ),
+            Status::InProgress | Status::Complete => {
+                let checked = matches!(self.status, Status::Complete);
+                interface.push(
+                    checkbox("", checked)
+                        .style(checkbox::success)
+                        .on_toggle(move |_| Message::ForwardSubtask(parent, id))
+                )
+            }
+        };
+
+        interface = interface.push(
+            button(text("Remove").size(14))
+                .style(button::danger)
+                .on_press(Message::RemoveSubtask(parent, id))
+        );
+
+        container(interface).padding(4).width(Fill).into()
+    }
+}
+
+impl App {
+    /// `update` for the window: handles the messages only iced produces and
+    /// starts background saves.
+    pub fn window_update(&mut self, msg: Message) -> iced::Task<Message> {
+        match msg {
+            Message::EditNotes(action) => {
+                let is_edit = action.is_edit();
+                self.list_editor.notes_editor.perform(action);
+                if is_edit {
+                    self.update(Message::NotesChanged(self.list_editor.notes_editor.text()));
+                }
+            }
+            Message::OpenNotes(id) => {
+                self.update(Message::OpenNotes(id));
+                let notes = self.selected
+                    .and_then(|list| self.doc.list(list))
+                    .and_then(|list| list.task(id))
+                    .and_then(|task| task.notes.clone());
+                self.list_editor.load_notes(&notes.unwrap_or_default());
+            }
+            Message::CloseRequested(id) => {
+                self.close();
+                return window::close(id);
+            }
+            Message::Tick => {
+                self.update(Message::Tick);
+                return self.save_if_settled();
+            }
+            msg => self.update(msg),
+        }
+        iced::Task::none()
+    }
+
+    /// Starts a background save if the last change is older than `SAVE_DEBOUNCE`.
+    fn save_if_settled(&mut self) -> iced::Task<Message> {
+        if self.saving
+            || self.conflict.is_some()
+            || self.last_change.is_none_or(|at| at.elapsed() < SAVE_DEBOUNCE) {
+            return iced::Task::none();
+        }
+        self.last_change = None;
+        let Some(snapshot) = self.snapshot() else {
+            return iced::Task::none();
+        };
+        self.on_disk = Self::disk_state(&self.doc.lists, &self.doc.trash, self.doc.selected_theme);
+        self.saving = true;
+        let (revision, written) = (self.revision, self.written.clone());
+        iced::Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || Self::write(snapshot, revision, &written))
+                    .await
+                    .unwrap_or_else(|e| Err(format!("Background save failed: {e}")))
+            },
+            Message::Saved,
+        )
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        let close = window::close_requests().map(Message::CloseRequested);
+        let poll = time::every(DISK_POLL).map(|_| Message::CheckDisk);
+        let keys = keyboard::on_key_press(Self::shortcut);
+        let mut subscriptions = vec![close, poll, keys];
+        #[cfg(unix)]
+        subscriptions.extend(self.rpc_requests());
+        subscriptions.extend(self.http_requests());
+        if (self.last_change.is_some() && !self.saving) || self.toast.is_some() {
+            subscriptions.push(time::every(SAVE_DEBOUNCE / 2).map(|_| Message::Tick));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    /// Calls arriving on the workspace's control socket. Read-only windows
+    /// leave the socket to the window that can save.
+    #[cfg(unix)]
+    fn rpc_requests(&self) -> Option<Subscription<Message>> {
+        use iced::futures::{SinkExt, StreamExt};
+
+        if self.read_only {
+            return None;
+        }
+        let socket = rpc::socket_path(&core::data_path(&self.workspace)?);
+        let requests = iced::stream::channel(16, |mut output| async move {
+            let (sender, mut requests) = iced::futures::channel::mpsc::unbounded();
+            let forward = move |request| sender.unbounded_send(Message::Rpc(request)).is_ok();
+            let _listener = match rpc::listen(&socket, forward) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log_error(&format!("Failed to open control socket {}: {e}", socket.display()));
+                    return;
+                }
+            };
+            while let Some(msg) = requests.next().await {
+                if output.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Some(Subscription::run_with_id(self.workspace.clone(), requests))
+    }
+
+    /// Requests to the HTTP API once it was started, answered by this
+    /// window for as long as it can save.
+    fn http_requests(&self) -> Option<Subscription<Message>> {
+        use iced::futures::{SinkExt, StreamExt};
+
+        let addr = self.server.filter(|_| !self.read_only)?;
+        let requests = iced::stream::channel(16, move |mut output| async move {
+            let (sender, mut requests) = iced::futures::channel::mpsc::unbounded();
+            let forward = move |request| sender.unbounded_send(Message::Http(request)).is_ok();
+            let _listener = match server::spawn(addr.port(), forward) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    let _ = output.send(Message::ServerFailed(e.to_string())).await;
+                    return;
+                }
+            };
+            while let Some(msg) = requests.next().await {
+                if output.send(msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Some(Subscription::run_with_id(addr, requests))
+    }
+
+    fn shortcut(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+        let keyboard::Key::Character(c) = key else {
+            return None;
+        };
+        if !modifiers.command() {
+            return None;
+        }
+        match c.to_lowercase().as_str() {
+            "z" if modifiers.shift() => Some(Message::Redo),
+            "z" => Some(Message::Undo),
+            "y" => Some(Message::Redo),
+            _ => None,
+        }
+    }
+
+    fn read_only_banner(&self) -> Option<Element<'_, Message>> {
+        if !self.read_only {
+            return None;
+        }
+        let owner = match self.other_instance {
+            Some(pid) => format!("another TaskMaster window (process {pid})"),
+            None => String::from("another TaskMaster window"),
+        };
+        let message = format!(
+            "This workspace is open in {owner}. This window is read-only and follows its changes; \
+             edits made here will not be saved."
+        );
+
+        Some(
+            container(
+                row![
+                    text(message).style(text::danger).width(Fill),
+                    button("Try again").on_press(Message::RetryLock),
+                ]
+                .spacing(16)
+                .align_y(Alignment::Center),
+            )
+            .padding(16)
+            .width(Fill)
+            .style(container::bordered_box)
+            .into(),
+        )
+    }
+
+    fn conflict_banner(&self) -> Option<Element<'_, Message>> {
+        self.conflict.as_ref()?;
+        let message = "Your lists file was changed outside TaskMaster while you had unsaved edits.";
+        let actions = row![
+            button("Keep mine").on_press(Message::KeepMine),
+            button("Take theirs").style(button::secondary).on_press(Message::TakeTheirs),
+            button("Merge").style(button::secondary).on_press(Message::MergeChanges),
+        ]
+        .spacing(8);
+
+        Some(
+            container(
+                row![text(message).style(text::danger).width(Fill), actions]
+                    .spacing(16)
+                    .align_y(Alignment::Center),
+            )
+            .padding(16)
+            .width(Fill)
+            .style(container::bordered_box)
+            .into(),
+        )
+    }
+
+    fn recovery_banner(&self) -> Option<Element<'_, Message>> {
+        let recovery = self.recovery.as_ref()?;
+        let mut message = match &recovery.quarantined {
+            Some(target) => format!(
+                "Your lists file could not be read. It was moved to {} and nothing has been lost.",
+                target.display()
+            ),
+            None => String::from(
+                "Your lists file could not be read and could not be moved aside. \
+                 Changes will not be saved until this is resolved."
+            ),
+        };
+        let mut actions = row![].spacing(8);
+        match &recovery.backup {
+            Some(lists) => {
+                message.push_str(&format!(" A backup with {} list(s) is available.", lists.len()));
+                actions = actions.push(button("Restore backup").on_press(Message::RestoreBackup));
+            }
+            None => message.push_str(" No readable backup was found."),
+        }
+        actions = actions.push(button("Dismiss").style(button::secondary).on_press(Message::DismissRecovery));
+
+        Some(
+            container(
+                row![text(message).style(text::danger).width(Fill), actions]
+                    .spacing(16)
+                    .align_y(Alignment::Center),
+            )
+            .padding(16)
+            .width(Fill)
+            .style(container::bordered_box)
+            .into(),
+        )
+    }
+
+    pub fn app_theme(&self) -> Theme {
+        match self.doc.selected_theme.unwrap_or(Themes::Default) {
+            Themes::Default => Theme::default(),
+            Themes::KanagawaWave => Theme::KanagawaWave,
+            Themes::Dark => Theme::Dark,
+            Themes::Light => Theme::Light,
+            Themes::Nord => Theme::Nord,
+            Themes::SolarizedDark => Theme::SolarizedDark,
+            Themes::SolarizedLight => Theme::SolarizedLight,
+            Themes::Ferra => Theme::Ferra,
+            Themes::Dracula => Theme::Dracula,
+            Themes::KanagawaDragon => Theme::KanagawaDragon,
+            Themes::KanagawaLotus => Theme::KanagawaLotus,
+            Themes::Moonfly => Theme::Moonfly,
+            Themes::Nightfly => Theme::Nightfly,
+            Themes::Oxocarbon => Theme::Oxocarbon,
+            Themes::TokyoNight => Theme::TokyoNight,
+            Themes::TokyoNightLight => Theme::TokyoNightLight,
+            Themes::TokyoNightStorm => Theme::TokyoNightStorm,
+            Themes::GruvboxDark => Theme::GruvboxDark,
+            Themes::GruvboxLight => Theme::GruvboxLight,
+        }
+    }
+
+    /// A button starting the HTTP API, or where it listens once started.
+    /// The title input for a new or renamed list, with the reason the last
+    /// title was rejected under it.
+    fn list_title_input(&self, on_save: Message, on_cancel: Message) -> Column<'_, Message> {
+        let input = row![
+            text_input("New list title...", &self.new_title)
+                .on_input(Message::UpdateListTitle)
+                .padding(8)
+                .width(Fill),
+            button("Save").on_press(on_save),
+            button("Cancel").style(button::danger).on_press(on_cancel),
+        ]
+        .spacing(8);
+        column![input].push_maybe(error_line(self.error.as_deref())).spacing(4)
+    }
+
+    fn server_status(&self) -> Element<'_, Message> {
+        match self.server {
+            Some(addr) => text(format!("API on http://{addr}")).style(text::secondary).into(),
+            None => button("Start API")
+                .style(button::secondary)
+                .on_press_maybe((!self.read_only).then_some(Message::StartServer))
+                .into(),
+        }
+    }
+
+    fn workspace_picker(&self) -> Element<'_, Message> {
+        row![
+            pick_list(self.workspaces.clone(), Some(self.workspace.clone()), Message::SwitchWorkspace),
+            button("+").style(button::secondary).on_press(Message::AddWorkspace),
+        ]
+        .spacing(4)
+        .align_y(Alignment::Center)
+        .into()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        column![]
+            .push_maybe(self.read_only_banner())
+            .push_maybe(self.recovery_banner())
+            .push_maybe(self.conflict_banner())
+            .push(self.content())
+            .push_maybe(self.toast_view())
+            .into()
+    }
+
+    fn toast_view(&self) -> Option<Element<'_, Message>> {
+        let (notice, _) = self.toast.as_ref()?;
+        Some(
+            container(
+                row![
+                    text(notice).width(Fill),
+                    button("Undo").on_press(Message::Undo),
+                    button("Dismiss").style(button::secondary).on_press(Message::DismissToast),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            )
+            .padding(12)
+            .width(Fill)
+            .style(container::bordered_box)
+            .into(),
+        )
+    }
+
+    fn backups_view<'a>(&'a self, backups: &'a [BackupEntry]) -> Element<'a, Message> {
+        let header = row![
+            container(text("Backups").size(48)).padding(16),
+            horizontal_space(),
+            container(button("Back to menu").on_press(Message::CloseBackups)).padding(16),
+        ]
+        .padding(16)
+        .align_y(Alignment::Center);
+
+        let mut interface = column![].spacing(12).padding(16);
+        if backups.is_empty() {
+            interface = interface.push(text("No backups yet. Snapshots are taken at most once an hour while you work.")
+                .style(text::secondary));
+        }
+
+        for entry in backups {
+            let summary = match entry.counts {
+                Some((lists, tasks)) => format!("{lists} list(s), {tasks} task(s)"),
+                None => String::from("unreadable"),
+            };
+            let mut line = row![
+                text(entry.taken_at.format(TIMESTAMP_FORMAT).to_string()).size(20),
+                text(summary).style(text::secondary),
+                horizontal_space(),
+            ]
+            .spacing(12)
+            .align_y(Alignment::Center);
+            if entry.counts.is_some() {
+                line = line
+                    .push(button("Preview").style(button::secondary).on_press(Message::PreviewBackup(entry.path.clone())))
+                    .push(button("Restore").on_press(Message::RestoreSnapshot(entry.path.clone())));
+            }
+            interface = interface.push(container(line).padding(8));
+
+            if let Some((path, lists)) = &self.backup_preview
+                && *path == entry.path {
+                let mut preview = column![].spacing(4).padding([0, 32]);
+                for list in lists {
+                    preview = preview.push(text(&list.title).size(18));
+                    for task in &list.list {
+                        preview = preview.push(
+                            text(format!("    {} - {:?}", task.title, task.status))
+                                .size(14)
+                                .style(text::secondary),
+                        );
+                    }
+                }
+                interface = interface.push(preview);
+            }
+        }
+
+        column![
+            header,
+            container(Rule::horizontal(1)).width(Fill),
+            scrollable(interface).height(Fill),
+        ]
+        .into()
+    }
+
+    fn trash_view(&self) -> Element<'_, Message> {
+        let header = row![
+            container(text("Trash").size(48)).padding(16),
+            horizontal_space(),
+            button("Empty trash").style(button::danger).on_press_maybe((!self.doc.trash.is_empty()).then_some(Message::EmptyTrash)),
+            container(button("Back to menu").on_press(Message::CloseTrash)).padding(16),
+        ]
+        .spacing(12)
+        .padding(16)
+        .align_y(Alignment::Center);
+
+        let mut interface = column![].spacing(12).padding(16);
+        let retention = match config::trash_days() {
+            0 => String::from("Items stay here until you delete them."),
+            days => format!("Items are deleted permanently after {days} day(s)."),
+        };
+        interface = interface.push(text(retention).style(text::secondary));
+
+        for item in self.doc.trash.iter().rev() {
+            let id = item.id();
+            let line = row![
+                text(item.describe()).size(20).wrapping(Wrapping::Word),
+                horizontal_space(),
+                text(item.deleted_at.format(TIMESTAMP_FORMAT).to_string()).style(text::secondary),
+                button("Restore").on_press(Message::RestoreFromTrash(id)),
+                button("Delete forever").style(button::danger).on_press(Message::DeleteForever(id)),
+            ]
+            .spacing(12)
+            .align_y(Alignment::Center);
+            interface = interface.push(container(line).padding(8));
+        }
+
+        column![
+            header,
+            container(Rule::horizontal(1)).width(Fill),
+            scrollable(interface).height(Fill),
+        ]
+        .into()
+    }
+
+    fn content(&self) -> Element<'_, Message> {
+        if let Some(backups) = &self.backups {
+            return self.backups_view(backups);
+        }
+        if self.trash_open {
+            return self.trash_view();
+        }
+        if let Some(selected) = self.selected.and_then(|id| self.doc.list(id)) {
+            let header = row![
+                container(text(format!("{}:", selected.title)).size(48)).padding(16),
+                text(format!("{}/{}",
+                    selected.list.iter()
+                        .filter(|t| t.status == Status::Complete).count(),
+                    selected.list.len())
+                ).style(text::secondary).size(48),
+            ]
+            .padding(16)
+            .align_y(Alignment::Center);
+
+            let mut col = column![
+                header,
+                row![
+                    container(button("Back to menu").on_press(Message::BackToLists)).padding(16),
+                    horizontal_space(),
+                    self.workspace_picker(),
+                    container(pick_list(self.doc.themes.clone(), self.doc.selected_theme, Message::ThemeChanged)
+                            .placeholder("Theme..."))
+                        .padding(16)
+                        .align_x(Alignment::End)
+                ],
+                container(Rule::horizontal(1)).width(Fill)
+            ];
+            let tasks_ui = self.list_editor.view(selected);
+            col = col.push(tasks_ui);
+            col.into()
+        } else {
+            let mut root = column![
+                row![
+                    container(text("Lists").size(48)).padding(16),
+                    horizontal_space(),
+                    button("Backups").style(button::secondary).on_press(Message::ShowBackups),
+                    button(text(format!("Trash ({})", self.doc.trash.len()))).style(button::secondary).on_press(Message::ShowTrash),
+                    self.server_status(),
+                    self.workspace_picker(),
+                    container(pick_list(self.doc.themes.clone(), self.doc.selected_theme, Message::ThemeChanged)
+                        .placeholder("Theme...")).align_x(Alignment::End)
+                ]
+                .spacing(12)
+                .padding(16)
+                .align_y(Alignment::Center)
+            ]
+            .spacing(16);
+
+            if let Some(name) = &self.new_workspace {
+                let input = row![
+                    text_input("New workspace name...", name)
+                        .on_input(Message::UpdateWorkspaceName)
+                        .on_submit(Message::ConfirmAddWorkspace)
+                        .padding(8)
+                        .width(Fill),
+                    button("Create").on_press(Message::ConfirmAddWorkspace),
+                    button("Cancel").style(button::danger).on_press(Message::CancelAddWorkspace),
+                ]
+                .spacing(8);
+                root = root.push(
+                    column![input]
+                        .push_maybe(error_line(self.workspace_error.as_deref()))
+                        .spacing(4)
+                        .padding([0, 16]),
+                );
+            }
+
+            root = root.push(container(Rule::horizontal(1)).width(Fill));
+
+            let tag_counts = self.doc.tag_counts();
+            if !tag_counts.is_empty() {
+                let overview = row![text("All tags").style(text::secondary)]
+                    .extend(tag_counts.into_iter().map(|(tag, count)| {
+                        container(text(format!("#{tag} ({count})")).size(14))
+                            .padding([2, 8])
+                            .style(container::rounded_box)
+                            .into()
+                    }))
+                    .spacing(8)
+                    .align_y(Alignment::Center);
+                root = root.push(container(overview.wrap()).padding([0, 32]));
+            }
+
+            let mut interface = column![].spacing(16).padding(16);
+
+            if self.doc.lists.is_empty() {
+                if self.adding_after == Some(0) {
+                    interface = interface.push(
+                        self.list_title_input(Message::ConfirmAddList, Message::CancelAddList),
+                    );
+                } else {
+                    interface = interface.push(button("Add List")
+                        .style(button::secondary)
+                        .on_press(Message::AddListAfter(0)));
+                }
+            }
+
+            for (i, lst) in self.doc.lists.iter().enumerate() {
+                let id = lst.id;
+                if i > 0 {
+                    interface = interface.push(container(Rule::horizontal(1)).width(Fill));
+                }
+
+                let row_line = row![
+                    text(&lst.title).size(30).wrapping(Wrapping::Word),
+                    horizontal_space(),
+                    button("Select").on_press(Message::SelectList(id)),
+                    button("Edit").style(button::secondary).on_press(Message::ChangeListTitle(id)),
+                    button("Remove").style(button::danger).on_press(Message::RemoveList(id)),
+                ]
+                .spacing(12)
+                .align_y(Alignment::Center);
+
+                interface = interface.push(container(row_line).padding(8));
+
+                if self.adding_after == Some(i) {
+                    interface = interface.push(
+                        self.list_title_input(Message::ConfirmAddList, Message::CancelAddList).padding(4),
+                    );
+                }
+
+                if self.editing == Some(lst.id) {
+                    interface = interface.push(
+                        self.list_title_input(Message::ConfirmListEdit, Message::CancelListEdit),
+                    );
+                }
+            }
+
+            if !self.doc.lists.is_empty() {
+                let end_index = self.doc.lists.len();
+                if self.adding_after == Some(end_index) {
+                    interface = interface.push(
+                        self.list_title_input(Message::ConfirmAddList, Message::CancelAddList).padding(4),
+                    );
+                } else {
+                    interface = interface.push(
+                        button("Add List")
+                            .style(button::secondary)
+                            .on_press(Message::AddListAfter(end_index)),
+                    );
+                }
+            }
+
+            let scrollable_lists = scrollable(interface.spacing(12)).height(Fill);
+
+            root.push(scrollable_lists).height(Fill).into()
+        }
+    }
+}
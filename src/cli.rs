@@ -0,0 +1,322 @@
+//! `TaskMaster <command>`: reads and edits the same data file as the window,
+//! for use from terminals and scripts. Edits take the workspace's lock and
+//! are refused while a window has it open.
+use crate::config;
+use crate::models::*;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: TaskMaster [--data-dir <path>] [--workspace <name>] [<command>] [--json]
+       TaskMaster [--data-dir <path>] [--workspace <name>] --serve [<port>]
+
+Without a command, opens the window. --serve answers HTTP requests on
+localhost instead (port 7878 unless given); see README.md for the routes.
+
+Commands:
+  list                              Show every list
+  show <list>                       Show the tasks in a list
+  add <list> <title>...             Add a task
+  start <list> <task>               Mark a task in progress
+  done <list> <task>                Mark a task complete
+  rm <list> <task>                  Move a task to the trash
+  rename <list> <title>             Rename a list
+  rename <list> <task> <title>      Rename a task
+  new-list <title>...               Create a list
+  tui                               Open the terminal frontend (needs the `tui` feature)
+  help                              Show this message
+
+Lists and tasks are given by their number in `list`/`show`, the start of
+their id, or their title. --json prints machine-readable output.";
+
+/// What the command line asks for.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    pub data_dir: Option<PathBuf>,
+    pub workspace: Option<String>,
+    pub command: Vec<String>,
+    pub json: bool,
+    /// Set by `--serve`, with the port if one was given.
+    pub serve: Option<Option<u16>>,
+}
+
+/// Parses the arguments after the program name. Options take their value
+/// as `--flag value` or `--flag=value`; `--serve` only takes the next
+/// argument if it is a number, so `--serve list` is not read as a port.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter().peekable();
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+            _ => (arg, None),
+        };
+        let mut value_of = |flag: &str| {
+            value.clone()
+                .or_else(|| args.next_if(|next| !next.starts_with("--")))
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| format!("{flag} needs a value"))
+        };
+        match flag.as_str() {
+            "--data-dir" => parsed.data_dir = Some(PathBuf::from(value_of("--data-dir")?)),
+            "--workspace" => parsed.workspace = Some(value_of("--workspace")?),
+            "--json" => parsed.json = true,
+            "--serve" => {
+                let port = value.clone().or_else(|| args.next_if(|next| next.bytes().all(|b| b.is_ascii_digit())));
+                let port = port.map(|p| p.parse::<u16>().map_err(|e| format!("invalid port {p:?}: {e}"))).transpose()?;
+                parsed.serve = Some(port);
+            }
+            "--help" | "-h" => parsed.command = vec![String::from("help")],
+            _ if flag.starts_with("--") => return Err(format!("unknown option {flag}")),
+            _ => parsed.command.push(flag),
+        }
+    }
+    if parsed.serve.is_some() && !parsed.command.is_empty() {
+        return Err(format!("--serve does not take a command, but got {:?}", parsed.command.join(" ")));
+    }
+    Ok(parsed)
+}
+
+/// Runs `command` with its `args`, printing the result to stdout.
+pub fn run(command: &str, args: &[String], json: bool) -> Result<(), String> {
+    if command == "help" {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let path = data_path()?;
+    let write = !matches!(command, "list" | "show");
+    let _lock = match write.then(|| crate::core::lock(&path)).transpose() {
+        Ok(lock) => lock,
+        Err(e @ crate::core::Error::Locked(_)) => {
+            return Err(format!("{e}; make the change there or close it first"));
+        }
+        Err(e) => return Err(format!("failed to lock {}: {e}", path.display())),
+    };
+    let mut app = if write { load(&path)? } else { read(&path)? };
+
+    let output = match (command, args) {
+        ("list", []) => {
+            if json {
+                json!(app.lists.iter().map(list_summary).collect::<Vec<_>>())
+            } else {
+                for (i, list) in app.lists.iter().enumerate() {
+                    let (done, total) = counts(list);
+                    println!("{:>3}. {} ({done}/{total})", i + 1, list.title);
+                }
+                return Ok(());
+            }
+        }
+        ("show", [list]) => {
+            let list = &app.lists[find_list(&app, list)?];
+            if json {
+                json!({ "id": list.id, "title": list.title, "tasks": list.list })
+            } else {
+                println!("{}:", list.title);
+                for (i, task) in list.list.iter().enumerate() {
+                    println!("{:>3}. {}", i + 1, describe(task));
+                    for sub in &task.subtasks {
+                        println!("       {}", describe(sub));
+                    }
+                }
+                return Ok(());
+            }
+        }
+        ("add", [list, title @ ..]) if !title.is_empty() => {
+            let index = find_list(&app, list)?;
+            let list = app.lists[index].id;
+            let id = app.add_task(list, &title.join(" ")).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            task_output(&app.lists[index], id, json, "Added")
+        }
+        ("start" | "done", [list, task]) => {
+            let (index, list, id) = find_list_and_task(&app, list, task)?;
+            let (status, verb) = match command {
+                "start" => (Status::InProgress, "Started"),
+                _ => (Status::Complete, "Completed"),
+            };
+            app.set_status(list, id, status).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            task_output(&app.lists[index], id, json, verb)
+        }
+        ("rm", [list, task]) => {
+            let (index, list, id) = find_list_and_task(&app, list, task)?;
+            let output = task_output(&app.lists[index], id, json, "Moved to trash");
+            app.remove_task(list, id).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            output
+        }
+        ("rename", [list, title]) => {
+            let index = find_list(&app, list)?;
+            app.rename_list(app.lists[index].id, title).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            list_output(&app.lists[index], json, "Renamed list")
+        }
+        ("rename", [list, task, title]) => {
+            let (index, list, id) = find_list_and_task(&app, list, task)?;
+            app.rename_task(list, id, title).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            task_output(&app.lists[index], id, json, "Renamed")
+        }
+        ("new-list", title) if !title.is_empty() => {
+            let id = app.add_list(&title.join(" ")).map_err(|e| e.to_string())?;
+            save(&path, &app)?;
+            list_output(app.list(id).ok_or("list vanished")?, json, "Created list")
+        }
+        _ => return Err(format!("invalid command: {command} {}\n\n{USAGE}", args.join(" "))),
+    };
+
+    if let serde_json::Value::String(line) = output {
+        println!("{line}");
+    } else {
+        println!("{}", serde_json::to_string_pretty(&output).map_err(|e| e.to_string())?);
+    }
+    Ok(())
+}
+
+fn data_path() -> Result<PathBuf, String> {
+    crate::core::data_path(&config::active_workspace())
+        .ok_or_else(|| String::from("could not resolve the data directory"))
+}
+
+fn load(path: &Path) -> Result<List, String> {
+    List::open(path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+}
+
+fn read(path: &Path) -> Result<List, String> {
+    List::read(path).map_err(|e| format!("failed to read {}: {e}", path.display()))
+}
+
+fn save(path: &Path, app: &List) -> Result<(), String> {
+    app.save_to(path).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+/// Resolves a list by 1-based number, id prefix, or title.
+pub(crate) fn find_list(app: &List, query: &str) -> Result<usize, String> {
+    find(app.lists.iter().map(|l| (l.id, l.title.as_str())), query)
+        .ok_or_else(|| format!("no list matches {query:?}"))
+}
+
+/// Resolves a task within `list` by 1-based number, id prefix, or title.
+pub(crate) fn find_task(list: &Tasks, query: &str) -> Result<usize, String> {
+    find(list.list.iter().map(|t| (t.id, t.title.as_str())), query)
+        .ok_or_else(|| format!("no task in {} matches {query:?}", list.title))
+}
+
+/// Resolves a list and a task within it, returning the list's index and
+/// both ids.
+fn find_list_and_task(app: &List, list: &str, task: &str) -> Result<(usize, ListId, TaskId), String> {
+    let index = find_list(app, list)?;
+    let list = &app.lists[index];
+    Ok((index, list.id, list.list[find_task(list, task)?].id))
+}
+
+fn find<'a>(items: impl Iterator<Item = (uuid::Uuid, &'a str)>, query: &str) -> Option<usize> {
+    let items: Vec<_> = items.collect();
+    if let Ok(n) = query.parse::<usize>()
+        && (1..=items.len()).contains(&n) {
+        return Some(n - 1);
+    }
+    let by_title = items.iter().position(|(_, title)| title.eq_ignore_ascii_case(query));
+    by_title.or_else(|| {
+        let mut matches = items.iter().enumerate().filter(|(_, (id, _))| {
+            query.len() >= 4 && id.to_string().starts_with(&query.to_ascii_lowercase())
+        });
+        match (matches.next(), matches.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    })
+}
+
+fn counts(list: &Tasks) -> (usize, usize) {
+    let done = list.list.iter().filter(|t| t.status == Status::Complete).count();
+    (done, list.list.len())
+}
+
+pub(crate) fn list_summary(list: &Tasks) -> serde_json::Value {
+    let (done, total) = counts(list);
+    json!({ "id": list.id, "title": list.title, "done": done, "total": total })
+}
+
+/// One-line summary of a task: status mark, title, priority, due date and tags.
+pub(crate) fn describe(task: &Task) -> String {
+    let mark = match task.status {
+        Status::Pending => "[ ]",
+        Status::InProgress => "[~]",
+        Status::Complete => "[x]",
+    };
+    let mut line = format!("{mark} {}", task.title);
+    if task.priority != Priority::None {
+        line.push_str(&format!("  !{}", task.priority));
+    }
+    if let Some(date) = task.due_date {
+        line.push_str(&format!("  due {}", date.format("%Y-%m-%d")));
+    }
+    for tag in &task.tags {
+        line.push_str(&format!("  #{tag}"));
+    }
+    line
+}
+
+fn task_output(list: &Tasks, id: TaskId, json: bool, verb: &str) -> serde_json::Value {
+    let Some(task) = list.task(id) else {
+        return json!(null);
+    };
+    if json {
+        json!(task)
+    } else {
+        json!(format!("{verb}: {}", describe(task)))
+    }
+}
+
+fn list_output(list: &Tasks, json: bool, verb: &str) -> serde_json::Value {
+    if json {
+        list_summary(list)
+    } else {
+        json!(format!("{verb}: {}", list.title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_items_by_number_title_or_id_prefix() {
+        let ids = [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()];
+        let items = || ids.iter().copied().zip(["Milk", "Eggs"]);
+
+        assert_eq!(find(items(), "2"), Some(1));
+        assert_eq!(find(items(), "milk"), Some(0));
+        assert_eq!(find(items(), &ids[1].to_string()[..8]), Some(1));
+        assert_eq!(find(items(), "3"), None);
+        assert_eq!(find(items(), "Bread"), None);
+    }
+
+    #[test]
+    fn options_need_their_values_and_serve_only_takes_a_number() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|a| a.to_string()));
+
+        let args = parse(&["--serve", "list"]);
+        assert!(args.is_err(), "a command after --serve is an error, not a port: {args:?}");
+        assert_eq!(parse(&["--serve", "8080"]).unwrap().serve, Some(Some(8080)));
+        assert_eq!(parse(&["--serve"]).unwrap().serve, Some(None));
+        assert_eq!(parse(&["--serve", "--json"]).unwrap().serve, Some(None));
+        assert!(parse(&["--serve=abc"]).is_err());
+        assert!(parse(&["--serve", "70000"]).is_err());
+
+        assert!(parse(&["--data-dir"]).is_err());
+        assert!(parse(&["--workspace", "--json", "list"]).is_err());
+        assert!(parse(&["--workspace="]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+
+        let args = parse(&["--workspace=work", "--data-dir", "/tmp/tasks", "show", "1", "--json"]).unwrap();
+        assert_eq!(args, Args {
+            data_dir: Some(PathBuf::from("/tmp/tasks")),
+            workspace: Some(String::from("work")),
+            command: vec![String::from("show"), String::from("1")],
+            json: true,
+            serve: None,
+        });
+    }
+}
@@ -0,0 +1,368 @@
+use serde::Deserialize;
+use std::{
+    ffi::OsString,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+const APP_DIR: &str = "taskmaster";
+/// Files and directories that make up a data directory.
+const DATA_ENTRIES: [&str; 7] = [
+    "lists.json",
+    "lists.json.bak",
+    "lists.db",
+    "lists.db-wal",
+    "lists.db-shm",
+    "todo.json",
+    "backups",
+];
+/// Left in the data directory while data is being moved into it, so a move
+/// that failed halfway is finished on the next launch.
+const MOVING_MARKER: &str = ".moving-from-tasks";
+
+/// The workspace whose data lives directly in the data directory.
+pub const DEFAULT_WORKSPACE: &str = "Default";
+
+static DATA_DIR_FLAG: OnceLock<PathBuf> = OnceLock::new();
+static WORKSPACE_FLAG: OnceLock<String> = OnceLock::new();
+static DATA_DIR: OnceLock<Option<DataDir>> = OnceLock::new();
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+#[derive(Default, Deserialize)]
+struct Settings {
+    data_dir: Option<PathBuf>,
+    #[serde(default)]
+    keep_undo_history: bool,
+    trash_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Source {
+    Flag,
+    Environment,
+    SettingsFile,
+    Default,
+}
+
+#[derive(Debug)]
+struct DataDir {
+    path: PathBuf,
+    source: Source,
+}
+
+/// Sets the data directory from the `--data-dir` command-line flag. Must be
+/// called before anything resolves a path.
+pub fn set_data_dir_flag(path: PathBuf) {
+    let _ = DATA_DIR_FLAG.set(path);
+}
+
+/// Sets the workspace to open from the `--workspace` command-line flag.
+pub fn set_workspace_flag(name: String) {
+    let _ = WORKSPACE_FLAG.set(name);
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// `$XDG_<VAR>` if set to an absolute path, otherwise `~/<fallback>`.
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home().map(|h| h.join(fallback)))
+        .map(|dir| dir.join(APP_DIR))
+}
+
+/// `~/Tasks`, where everything lived before XDG support.
+pub fn legacy_dir() -> Option<PathBuf> {
+    home().map(|h| h.join("Tasks"))
+}
+
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        xdg_dir("XDG_CONFIG_HOME", ".config")
+    } else {
+        legacy_dir()
+    }
+}
+
+fn default_data_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        xdg_dir("XDG_DATA_HOME", ".local/share")
+    } else {
+        legacy_dir()
+    }
+}
+
+/// Where logs are written: `$XDG_STATE_HOME/taskmaster` on Linux, `~/Tasks/bin`
+/// elsewhere.
+pub fn state_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "linux") {
+        xdg_dir("XDG_STATE_HOME", ".local/state")
+    } else {
+        legacy_dir().map(|d| d.join("bin"))
+    }
+}
+
+/// `settings.json` in the config directory.
+pub fn settings_path() -> Option<PathBuf> {
+    config_dir().map(|d| d.join("settings.json"))
+}
+
+/// The settings file, read the first time it is needed; edits take effect
+/// on the next start.
+fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(|| {
+        settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    })
+}
+
+/// Whether undo history is written to disk so it survives restarts. Off
+/// unless `keep_undo_history` is set in the settings file.
+pub fn keep_undo_history() -> bool {
+    settings().keep_undo_history
+}
+
+/// Days a deleted item stays in the trash before it is purged, from
+/// `trash_days` in the settings file. Zero keeps items until emptied by hand.
+pub fn trash_days() -> u32 {
+    settings().trash_days.unwrap_or(30)
+}
+
+fn resolve() -> Option<DataDir> {
+    choose_data_dir(
+        DATA_DIR_FLAG.get().cloned(),
+        std::env::var_os("TASKMASTER_DATA"),
+        || settings().data_dir.clone(),
+        default_data_dir,
+    )
+}
+
+/// The first data directory that is set, in order of precedence.
+fn choose_data_dir(
+    flag: Option<PathBuf>,
+    env: Option<OsString>,
+    settings: impl FnOnce() -> Option<PathBuf>,
+    default: impl FnOnce() -> Option<PathBuf>,
+) -> Option<DataDir> {
+    if let Some(path) = flag {
+        return Some(DataDir { path, source: Source::Flag });
+    }
+    if let Some(path) = env.filter(|p| !p.is_empty()) {
+        return Some(DataDir { path: PathBuf::from(path), source: Source::Environment });
+    }
+    if let Some(path) = settings() {
+        return Some(DataDir { path, source: Source::SettingsFile });
+    }
+    default().map(|path| DataDir { path, source: Source::Default })
+}
+
+/// The directory holding `lists.json` and its backups. In order of
+/// precedence: `--data-dir`, `$TASKMASTER_DATA`, `data_dir` in the settings
+/// file, then the platform default.
+pub fn data_dir() -> Option<PathBuf> {
+    DATA_DIR.get_or_init(resolve).as_ref().map(|d| d.path.clone())
+}
+
+/// Moves data from `~/Tasks` into the default data directory the first time
+/// it is used. Does nothing when the location was configured explicitly, or
+/// when the default directory already holds data of its own. Returns the new
+/// location if anything was moved.
+pub fn migrate_legacy_data() -> io::Result<Option<PathBuf>> {
+    let Some(DataDir { path: target, source: Source::Default }) = DATA_DIR.get_or_init(resolve) else {
+        return Ok(None);
+    };
+    let Some(legacy) = legacy_dir() else {
+        return Ok(None);
+    };
+    if legacy == *target || !move_data(&legacy, target)? {
+        return Ok(None);
+    }
+    Ok(Some(target.clone()))
+}
+
+/// Moves every data entry from `legacy` into `target`, unless `target`
+/// already holds data that did not come from an unfinished earlier move.
+fn move_data(legacy: &Path, target: &Path) -> io::Result<bool> {
+    let has_data = |dir: &Path| DATA_ENTRIES.iter().any(|entry| dir.join(entry).exists());
+    let marker = target.join(MOVING_MARKER);
+    let resuming = marker.exists();
+    if (!resuming && has_data(target)) || !has_data(legacy) {
+        if resuming {
+            fs::remove_file(&marker)?;
+        }
+        return Ok(false);
+    }
+
+    fs::create_dir_all(target)?;
+    fs::write(&marker, b"")?;
+    for entry in DATA_ENTRIES {
+        let from = legacy.join(entry);
+        if from.exists() {
+            move_entry(&from, &target.join(entry))?;
+        }
+    }
+    fs::remove_file(&marker)?;
+    Ok(true)
+}
+
+/// `fs::rename`, falling back to copy-and-delete across file systems.
+fn move_entry(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for child in fs::read_dir(from)? {
+            let child = child?;
+            move_entry(&child.path(), &to.join(child.file_name()))?;
+        }
+        fs::remove_dir(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Workspace names double as directory names, so they must not contain path
+/// separators or be empty.
+pub fn is_valid_workspace(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name == name.trim()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\'])
+}
+
+/// The directory holding a workspace's data file and backups. The default
+/// workspace uses the data directory itself so existing data keeps working.
+pub fn workspace_dir(name: &str) -> Option<PathBuf> {
+    let dir = data_dir()?;
+    if name == DEFAULT_WORKSPACE {
+        Some(dir)
+    } else {
+        Some(dir.join("workspaces").join(name))
+    }
+}
+
+/// Every workspace on disk, default first, then alphabetically.
+pub fn workspaces() -> Vec<String> {
+    let mut names: Vec<String> = data_dir()
+        .and_then(|dir| fs::read_dir(dir.join("workspaces")).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_valid_workspace(name) && name != DEFAULT_WORKSPACE)
+        .collect();
+    names.sort();
+    names.insert(0, String::from(DEFAULT_WORKSPACE));
+    names
+}
+
+fn last_workspace_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("last_workspace"))
+}
+
+/// The workspace to open on launch: `--workspace`, then the one used last.
+pub fn active_workspace() -> String {
+    WORKSPACE_FLAG
+        .get()
+        .cloned()
+        .or_else(|| last_workspace_path().and_then(|path| fs::read_to_string(path).ok()))
+        .map(|name| name.trim().to_string())
+        .filter(|name| is_valid_workspace(name))
+        .unwrap_or_else(|| String::from(DEFAULT_WORKSPACE))
+}
+
+/// Records `name` so the next launch opens it.
+pub fn remember_workspace(name: &str) -> io::Result<()> {
+    let Some(path) = last_workspace_path() else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_names_cannot_escape_the_data_directory() {
+        assert!(is_valid_workspace("Work"));
+        assert!(is_valid_workspace("side project"));
+        for name in ["", " ", " Work", ".", "..", "a/b", "a\\b"] {
+            assert!(!is_valid_workspace(name), "{name:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn flag_beats_environment_beats_settings_beats_default() {
+        let dir = |name: &str| Some(PathBuf::from(name));
+        let pick = |flag, env: Option<&str>, settings| {
+            let chosen = choose_data_dir(flag, env.map(OsString::from), || settings, || dir("default")).unwrap();
+            (chosen.path, chosen.source)
+        };
+
+        assert_eq!(pick(dir("flag"), Some("env"), dir("settings")), (PathBuf::from("flag"), Source::Flag));
+        assert_eq!(pick(None, Some("env"), dir("settings")), (PathBuf::from("env"), Source::Environment));
+        assert_eq!(pick(None, Some(""), dir("settings")), (PathBuf::from("settings"), Source::SettingsFile));
+        assert_eq!(pick(None, None, None), (PathBuf::from("default"), Source::Default));
+    }
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn moves_the_database_with_its_journal() {
+        let root = scratch_dir();
+        let (legacy, target) = (root.join("Tasks"), root.join("taskmaster"));
+        fs::create_dir_all(legacy.join("backups")).unwrap();
+        for entry in ["lists.db", "lists.db-wal", "lists.db-shm", "backups/lists-20260101-000000.db"] {
+            fs::write(legacy.join(entry), entry).unwrap();
+        }
+
+        assert!(move_data(&legacy, &target).unwrap());
+
+        for entry in ["lists.db", "lists.db-wal", "lists.db-shm", "backups/lists-20260101-000000.db"] {
+            assert_eq!(fs::read_to_string(target.join(entry)).unwrap(), entry);
+            assert!(!legacy.join(entry).exists(), "{entry}");
+        }
+        assert!(!target.join(MOVING_MARKER).exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn finishes_a_move_that_failed_halfway() {
+        let root = scratch_dir();
+        let (legacy, target) = (root.join("Tasks"), root.join("taskmaster"));
+        fs::create_dir_all(&legacy).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join(MOVING_MARKER), b"").unwrap();
+        fs::write(target.join("lists.json"), b"moved").unwrap();
+        fs::write(legacy.join("lists.json.bak"), b"left behind").unwrap();
+
+        assert!(move_data(&legacy, &target).unwrap());
+
+        assert_eq!(fs::read(target.join("lists.json.bak")).unwrap(), b"left behind");
+        assert!(!target.join(MOVING_MARKER).exists());
+        fs::write(legacy.join("todo.json"), b"{}").unwrap();
+        assert!(!move_data(&legacy, &target).unwrap(), "a finished move is not repeated");
+        fs::remove_dir_all(root).unwrap();
+    }
+}
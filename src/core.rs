@@ -0,0 +1,1543 @@
+//! The task model without any user interface: typed operations on lists and
+//! tasks, and reading and writing the data file. The window, the terminal
+//! frontend and the command line are all built on this.
+use crate::config;
+use crate::migrations;
+use crate::models::*;
+use crate::storage::{self, StorageError};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+pub const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Debug)]
+pub enum Error {
+    ListNotFound(ListId),
+    TaskNotFound(TaskId),
+    NotInTrash(Uuid),
+    /// Lists and tasks need a title that is not just whitespace.
+    EmptyTitle,
+    /// Typed text that does not read as the date, time or rule it stands for.
+    BadInput(String),
+    Storage(StorageError),
+    /// The stored document could not be migrated or parsed.
+    Invalid(String),
+    /// A window has the workspace open; carries its pid if it could be read.
+    Locked(Option<u32>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ListNotFound(id) => write!(f, "no list with id {id}"),
+            Error::TaskNotFound(id) => write!(f, "no task with id {id}"),
+            Error::NotInTrash(id) => write!(f, "nothing with id {id} in the trash"),
+            Error::EmptyTitle => write!(f, "title must not be empty"),
+            Error::BadInput(e) => write!(f, "{e}"),
+            Error::Storage(e) => write!(f, "{e}"),
+            Error::Invalid(e) => write!(f, "unreadable data: {e}"),
+            Error::Locked(Some(pid)) => write!(f, "the workspace is open in TaskMaster (process {pid})"),
+            Error::Locked(None) => write!(f, "the workspace is open in TaskMaster"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<StorageError> for Error {
+    fn from(e: StorageError) -> Self {
+        Error::Storage(e)
+    }
+}
+
+/// Where `workspace` keeps its data file.
+pub fn data_path(workspace: &str) -> Option<PathBuf> {
+    config::workspace_dir(workspace).map(|dir| dir.join(storage::data_file_name()))
+}
+
+/// Takes the lock on the data file at `path` for a one-off edit from outside
+/// the window, creating its directory if needed.
+pub fn lock(path: &Path) -> Result<fs::File, Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(StorageError::from)?;
+    }
+    storage::lock(path).map_err(|e| match e {
+        storage::LockError::Held(pid) => Error::Locked(pid),
+        storage::LockError::Io(e) => Error::Storage(e.into()),
+    })
+}
+
+fn title(input: &str) -> Result<String, Error> {
+    let title = input.trim();
+    if title.is_empty() {
+        Err(Error::EmptyTitle)
+    } else {
+        Ok(title.to_string())
+    }
+}
+
+pub fn parse_due_date(input: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(input.trim(), DATE_FORMAT).ok()
+}
+
+pub fn parse_due_time(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input.trim(), TIME_FORMAT).ok()
+}
+
+/// Splits a tag input such as `"#backend, ops"` into `["backend", "ops"]`.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        let tag = tag.trim_start_matches('#');
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Parses a repeat rule such as `"daily"`, `"weekly mon,fri"`,
+/// `"monthly 15"` or `"every 3 days"`.
+pub fn parse_recurrence(input: &str) -> Option<Recurrence> {
+    let input = input.trim().to_lowercase();
+    let (kind, rest) = input.split_once(char::is_whitespace).unwrap_or((&input, ""));
+    let rest = rest.trim();
+    match kind {
+        "daily" => Some(Recurrence::Daily),
+        "weekly" => {
+            let days = rest
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|d| !d.is_empty())
+                .map(|d| d.parse::<Weekday>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            Some(Recurrence::Weekly(days))
+        }
+        "monthly" => rest.parse().ok()
+            .filter(|day| (1..=31).contains(day))
+            .map(Recurrence::Monthly),
+        "every" => rest.trim_end_matches("days").trim_end_matches("day").trim().parse().ok()
+            .filter(|n| *n > 0)
+            .map(Recurrence::EveryNDays),
+        _ => None,
+    }
+}
+
+impl Display for Themes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Themes {
+    /// The colors iced draws this theme with, so frontends without iced
+    /// can match the window.
+    pub fn palette(self) -> Palette {
+        let [background, text, primary, success, danger] = match self {
+            Themes::Default | Themes::Light => [0xFFFFFF, 0x000000, 0x5E7CE2, 0x12664F, 0xC3423F],
+            Themes::Dark => [0x202225, 0xE6E6E6, 0x5E7CE2, 0x12664F, 0xC3423F],
+            Themes::Dracula => [0x282A36, 0xF8F8F2, 0xBD93F9, 0x50FA7B, 0xFF5555],
+            Themes::Nord => [0x2E3440, 0xECEFF4, 0x8FBCBB, 0xA3BE8C, 0xBF616A],
+            Themes::SolarizedLight => [0xFDF6E3, 0x657B83, 0x2AA198, 0x859900, 0xDC322F],
+            Themes::SolarizedDark => [0x002B36, 0x839496, 0x2AA198, 0x859900, 0xDC322F],
+            Themes::GruvboxLight => [0xFBF1C7, 0x282828, 0x458588, 0x98971A, 0xCC241D],
+            Themes::GruvboxDark => [0x282828, 0xFBF1C7, 0x458588, 0x98971A, 0xCC241D],
+            Themes::TokyoNight => [0x1A1B26, 0x9AA5CE, 0x2AC3DE, 0x9ECE6A, 0xF7768E],
+            Themes::TokyoNightStorm => [0x24283B, 0x9AA5CE, 0x2AC3DE, 0x9ECE6A, 0xF7768E],
+            Themes::TokyoNightLight => [0xD5D6DB, 0x565A6E, 0x166775, 0x485E30, 0x8C4351],
+            Themes::KanagawaWave => [0x363646, 0xDCD7BA, 0x2D4F67, 0x76946A, 0xC34043],
+            Themes::KanagawaDragon => [0x181616, 0xC5C9C5, 0x223249, 0x8A9A7B, 0xC4746E],
+            Themes::KanagawaLotus => [0xF2ECBC, 0x545464, 0xC9CBD1, 0x6F894E, 0xC84053],
+            Themes::Moonfly => [0x080808, 0xBDBDBD, 0x80A0FF, 0x8CC85F, 0xFF5454],
+            Themes::Nightfly => [0x011627, 0xBDC1C6, 0x82AAFF, 0xA1CD5E, 0xFC514E],
+            Themes::Oxocarbon => [0x232323, 0xD0D0D0, 0x00B4FF, 0x00C15A, 0xF62D0F],
+            Themes::Ferra => [0x2B292D, 0xFECDB2, 0xD1D1E0, 0xB1B695, 0xE06B75],
+        }
+        .map(|hex: u32| {
+            let [_, r, g, b] = hex.to_be_bytes();
+            [r, g, b]
+        });
+        Palette { background, text, primary, success, danger }
+    }
+}
+
+impl Status {
+    /// The status the window's status button moves a task to: pending tasks
+    /// are started, started ones completed, and completed ones reopened.
+    pub fn next(self) -> Status {
+        match self {
+            Status::Pending => Status::InProgress,
+            Status::InProgress => Status::Complete,
+            Status::Complete => Status::InProgress,
+        }
+    }
+}
+
+impl Display for Priority {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly(days) if days.is_empty() => write!(f, "weekly"),
+            Recurrence::Weekly(days) => {
+                let days: Vec<String> = days.iter().map(|d| d.to_string().to_lowercase()).collect();
+                write!(f, "weekly {}", days.join(","))
+            }
+            Recurrence::Monthly(day) => write!(f, "monthly {day}"),
+            Recurrence::EveryNDays(n) => write!(f, "every {n} days"),
+        }
+    }
+}
+
+impl Recurrence {
+    /// The first occurrence strictly after `date`.
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Days::new(1),
+            Recurrence::Weekly(days) if days.is_empty() => date + Days::new(7),
+            Recurrence::Weekly(days) => (1..=7)
+                .map(|offset| date + Days::new(offset))
+                .find(|d| days.contains(&d.weekday()))
+                .unwrap_or(date + Days::new(7)),
+            Recurrence::Monthly(day) => {
+                let in_month = |month: NaiveDate| {
+                    (1..=*day).rev()
+                        .find_map(|d| month.with_day(d))
+                        .unwrap_or(month)
+                };
+                let this_month = in_month(date.with_day(1).unwrap_or(date));
+                if this_month > date {
+                    this_month
+                } else {
+                    in_month(date.with_day(1).unwrap_or(date) + Months::new(1))
+                }
+            }
+            Recurrence::EveryNDays(n) => date + Days::new(u64::from((*n).max(1))),
+        }
+    }
+}
+
+impl Display for SortOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Insertion => write!(f, "Insertion order"),
+            SortOrder::Priority => write!(f, "Priority"),
+            SortOrder::Status => write!(f, "Status"),
+        }
+    }
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [
+        SortOrder::Insertion,
+        SortOrder::Priority,
+        SortOrder::Status,
+    ];
+}
+
+impl TrashItem {
+    /// Id of the deleted task or list.
+    pub fn id(&self) -> Uuid {
+        match &self.item {
+            Trashed::Task { task, .. } => task.id,
+            Trashed::List { list, .. } => list.id,
+        }
+    }
+}
+
+/// Merges `theirs` into `mine`, matching lists and tasks by id, with `base`
+/// as the version both sides started from: lists and tasks they added,
+/// deleted or edited are added, deleted or edited in `mine` too. Where both
+/// sides changed the same field, or one side edited what the other deleted,
+/// `mine` wins.
+pub fn merge_lists(base: &[Tasks], mine: &mut Vec<Tasks>, theirs: Vec<Tasks>) {
+    mine.retain(|list| {
+        theirs.iter().any(|l| l.id == list.id) || !base.iter().any(|b| b.id == list.id && same(b, list))
+    });
+    for their_list in theirs {
+        let base_list = base.iter().find(|l| l.id == their_list.id);
+        match mine.iter_mut().find(|l| l.id == their_list.id) {
+            Some(my_list) => merge_list(base_list, my_list, their_list),
+            // Deleted on my side.
+            None if base_list.is_some() => {}
+            None => mine.push(their_list),
+        }
+    }
+}
+
+fn merge_list(base: Option<&Tasks>, mine: &mut Tasks, theirs: Tasks) {
+    let base_tasks = base.map_or(&[][..], |b| &b.list);
+    if let Some(base) = base {
+        if mine.title == base.title {
+            mine.title = theirs.title;
+        }
+        if mine.sort_order == base.sort_order {
+            mine.sort_order = theirs.sort_order;
+        }
+    }
+    mine.list.retain(|task| {
+        theirs.list.iter().any(|t| t.id == task.id) || !base_tasks.iter().any(|b| b.id == task.id && same(b, task))
+    });
+    for task in theirs.list {
+        let base_task = base_tasks.iter().find(|t| t.id == task.id);
+        match (mine.task_mut(task.id), base_task) {
+            (Some(my_task), Some(base_task)) => *my_task = merge_fields(base_task, my_task, &task),
+            // Added on both sides, or deleted on mine.
+            (Some(_), None) | (None, Some(_)) => {}
+            (None, None) => mine.list.push(task),
+        }
+    }
+}
+
+/// Takes each field from `theirs` unless `mine` changed it since `base`.
+fn merge_fields<T: Serialize + DeserializeOwned + Clone>(base: &T, mine: &T, theirs: &T) -> T {
+    let values = (serde_json::to_value(base), serde_json::to_value(mine), serde_json::to_value(theirs));
+    let (Ok(Value::Object(base)), Ok(Value::Object(mut merged)), Ok(Value::Object(theirs))) = values else {
+        return mine.clone();
+    };
+    for (field, value) in theirs {
+        if merged.get(&field) == base.get(&field) {
+            merged.insert(field, value);
+        }
+    }
+    serde_json::from_value(Value::Object(merged)).unwrap_or_else(|_| mine.clone())
+}
+
+fn same<T: Serialize>(a: &T, b: &T) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// Whether a list, task or subtask with `id` is in `lists`.
+fn contains_id(lists: &[Tasks], id: Uuid) -> bool {
+    lists.iter().any(|list| {
+        list.id == id || list.list.iter().any(|t| t.id == id || t.subtasks.iter().any(|s| s.id == id))
+    })
+}
+
+impl Task {
+    pub fn new(title: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title,
+            created_at: Some(Local::now()),
+            ..Self::default()
+        }
+    }
+
+    /// Moves the task to `status`, logging the change and stamping when it
+    /// was first started and when it was completed.
+    pub fn set_status(&mut self, status: Status) {
+        if status == self.status {
+            return;
+        }
+        let now = Local::now();
+        self.history.push(StatusChange { from: self.status, to: status, at: now });
+        if status == Status::InProgress && self.started_at.is_none() {
+            self.started_at = Some(now);
+        }
+        self.completed_at = (status == Status::Complete).then_some(now);
+        self.status = status;
+    }
+
+    /// Advances the task's status: pending tasks are started, started ones
+    /// completed, and completed ones reopened. Completing a recurring task
+    /// returns its next occurrence; the completed task stays behind as
+    /// history and no longer recurs itself.
+    pub fn advance(&mut self) -> Option<Task> {
+        self.set_status(self.status.next());
+        if self.status == Status::Complete {
+            return self.next_occurrence(Local::now().date_naive());
+        }
+        None
+    }
+
+    /// A pending copy of the task with fresh timestamps and no history.
+    fn fresh_copy(&self) -> Task {
+        Task {
+            id: Uuid::new_v4(),
+            status: Status::Pending,
+            subtasks: self.subtasks.iter().map(Task::fresh_copy).collect(),
+            created_at: Some(Local::now()),
+            started_at: None,
+            completed_at: None,
+            history: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// A task is overdue once its due date has passed, or once its due time
+    /// has passed on the due date itself. Completed tasks are never overdue.
+    pub fn is_overdue(&self, now: NaiveDateTime) -> bool {
+        if self.status == Status::Complete {
+            return false;
+        }
+        match (self.due_date, self.due_time) {
+            (Some(date), Some(time)) => date.and_time(time) < now,
+            (Some(date), None) => date < now.date(),
+            _ => false,
+        }
+    }
+
+    /// Number of completed subtasks and the total number of subtasks.
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self.subtasks.iter().filter(|t| t.status == Status::Complete).count();
+        (done, self.subtasks.len())
+    }
+
+    /// Completes the task once every subtask is complete, and reopens it when
+    /// a subtask is reopened after the task was completed.
+    fn sync_with_subtasks(&mut self) {
+        let (done, total) = self.progress();
+        if total == 0 {
+            return;
+        }
+        if done == total {
+            self.set_status(Status::Complete);
+        } else if self.status == Status::Complete {
+            self.set_status(Status::InProgress);
+        }
+    }
+
+    pub fn is_due_today(&self, now: NaiveDateTime) -> bool {
+        self.status != Status::Complete
+            && self.due_date == Some(now.date())
+            && !self.is_overdue(now)
+    }
+
+    /// Takes the recurrence off a completed task and returns a fresh copy
+    /// due on the next occurrence that is not in the past.
+    pub fn next_occurrence(&mut self, today: NaiveDate) -> Option<Task> {
+        let recurrence = self.recurrence.take()?;
+        let mut due = recurrence.next_after(self.due_date.unwrap_or(today));
+        while due < today {
+            due = recurrence.next_after(due);
+        }
+        Some(Task {
+            due_date: Some(due),
+            collapsed: false,
+            recurrence: Some(recurrence),
+            ..self.fresh_copy()
+        })
+    }
+}
+
+impl Default for Tasks {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title: String::from("Untitled"),
+            list: Vec::new(),
+            sort_order: SortOrder::default(),
+        }
+    }
+}
+
+impl Tasks {
+    pub fn task(&self, id: TaskId) -> Option<&Task> {
+        self.list.iter().find(|t| t.id == id)
+    }
+
+    pub fn task_mut(&mut self, id: TaskId) -> Option<&mut Task> {
+        self.list.iter_mut().find(|t| t.id == id)
+    }
+
+    fn get_mut(&mut self, id: TaskId) -> Result<&mut Task, Error> {
+        self.task_mut(id).ok_or(Error::TaskNotFound(id))
+    }
+
+    /// Indices into `list` in display order, skipping tasks that lack any
+    /// tag in `tag_filter`. Sorting never reorders the underlying `Vec`.
+    pub fn display_order(&self, tag_filter: &[String]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.list.len())
+            .filter(|&i| tag_filter.iter().all(|tag| self.list[i].tags.contains(tag)))
+            .collect();
+        match self.sort_order {
+            SortOrder::Insertion => {}
+            SortOrder::Priority => order.sort_by_key(|&i| std::cmp::Reverse(self.list[i].priority)),
+            SortOrder::Status => order.sort_by_key(|&i| self.list[i].status),
+        }
+        order
+    }
+
+    /// Every distinct tag used in this list, sorted alphabetically.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self.list.iter()
+            .flat_map(|t| t.tags.iter().map(String::as_str))
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// Appends `task`, returning its id.
+    pub fn add(&mut self, task: Task) -> Result<TaskId, Error> {
+        let task = Task { title: title(&task.title)?, ..task };
+        let id = task.id;
+        self.list.push(task);
+        Ok(id)
+    }
+
+    pub fn rename(&mut self, id: TaskId, new_title: &str) -> Result<(), Error> {
+        let new_title = title(new_title)?;
+        self.get_mut(id)?.title = new_title;
+        Ok(())
+    }
+
+    /// Moves a task to `status`. Completing a recurring task adds its next
+    /// occurrence to the list and returns the new task's id.
+    pub fn set_status(&mut self, id: TaskId, status: Status) -> Result<Option<TaskId>, Error> {
+        let task = self.get_mut(id)?;
+        task.set_status(status);
+        let next = match status {
+            Status::Complete => task.next_occurrence(Local::now().date_naive()),
+            _ => None,
+        };
+        Ok(self.push_occurrence(next))
+    }
+
+    /// Advances a task as described in `Task::advance`, returning the id of
+    /// the next occurrence if one was added.
+    pub fn advance(&mut self, id: TaskId) -> Result<Option<TaskId>, Error> {
+        let next = self.get_mut(id)?.advance();
+        Ok(self.push_occurrence(next))
+    }
+
+    fn push_occurrence(&mut self, next: Option<Task>) -> Option<TaskId> {
+        let next = next?;
+        let id = next.id;
+        self.list.push(next);
+        Some(id)
+    }
+
+    pub fn set_priority(&mut self, id: TaskId, priority: Priority) -> Result<(), Error> {
+        self.get_mut(id)?.priority = priority;
+        Ok(())
+    }
+
+    /// Takes a task out of the list, returning it in the form the trash keeps.
+    pub fn remove(&mut self, id: TaskId) -> Result<Trashed, Error> {
+        let position = self.list.iter().position(|t| t.id == id).ok_or(Error::TaskNotFound(id))?;
+        Ok(Trashed::Task {
+            task: Box::new(self.list.remove(position)),
+            list: self.id,
+            list_title: self.title.clone(),
+            parent: None,
+            position,
+        })
+    }
+
+    pub fn add_subtask(&mut self, id: TaskId, new_title: &str) -> Result<TaskId, Error> {
+        let subtask = Task::new(title(new_title)?);
+        let sub = subtask.id;
+        let task = self.get_mut(id)?;
+        task.subtasks.push(subtask);
+        task.collapsed = false;
+        task.sync_with_subtasks();
+        Ok(sub)
+    }
+
+    /// Advances a subtask. The parent completes along with its last subtask,
+    /// and if it recurs, its next occurrence is added and its id returned.
+    pub fn advance_subtask(&mut self, id: TaskId, sub: TaskId) -> Result<Option<TaskId>, Error> {
+        let task = self.get_mut(id)?;
+        task.subtasks.iter_mut()
+            .find(|t| t.id == sub)
+            .ok_or(Error::TaskNotFound(sub))?
+            .advance();
+        let was_complete = task.status == Status::Complete;
+        task.sync_with_subtasks();
+        let next = if !was_complete && task.status == Status::Complete {
+            task.next_occurrence(Local::now().date_naive())
+        } else {
+            None
+        };
+        Ok(self.push_occurrence(next))
+    }
+
+    pub fn remove_subtask(&mut self, id: TaskId, sub: TaskId) -> Result<Trashed, Error> {
+        let (list, list_title) = (self.id, self.title.clone());
+        let task = self.get_mut(id)?;
+        let position = task.subtasks.iter().position(|t| t.id == sub).ok_or(Error::TaskNotFound(sub))?;
+        let removed = task.subtasks.remove(position);
+        task.sync_with_subtasks();
+        Ok(Trashed::Task { task: Box::new(removed), list, list_title, parent: Some(id), position })
+    }
+}
+
+impl Default for List {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl List {
+    /// An empty document.
+    pub fn new() -> Self {
+        Self {
+            schema_version: migrations::CURRENT_VERSION,
+            lists: Vec::new(),
+            themes: Vec::new(),
+            selected_theme: None,
+            trash: Vec::new(),
+            journal: None,
+        }
+    }
+
+    /// Builds the document from stored data of any known schema version,
+    /// migrating it to the current one.
+    pub fn from_document(doc: serde_json::Value) -> Result<Self, String> {
+        let doc = migrations::migrate(doc)?;
+        serde_json::from_value(doc).map_err(|e| e.to_string())
+    }
+
+    /// Reads the document stored at `path`, or an empty one if nothing has
+    /// been stored there yet. Unlike the window, an unreadable file is
+    /// reported rather than moved aside. A file from an older version is
+    /// written back migrated, so the ids it was given stay the same; the
+    /// caller must hold the lock from [`lock`].
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let backend = storage::open(path)?;
+        let Some(doc) = backend.load()? else {
+            return Ok(Self::new());
+        };
+        let outdated = migrations::is_outdated(&doc);
+        let list = Self::from_document(doc).map_err(Error::Invalid)?;
+        if outdated {
+            let doc = serde_json::to_value(&list).map_err(|e| Error::Invalid(e.to_string()))?;
+            backend.save(&doc)?;
+        }
+        Ok(list)
+    }
+
+    /// Reads the document stored at `path` like [`List::open`], but never
+    /// writes, for reading while someone else holds the lock.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        match storage::open_read_only(path)?.load()? {
+            Some(doc) => Self::from_document(doc).map_err(Error::Invalid),
+            None => Ok(Self::new()),
+        }
+    }
+
+    /// Writes the document to `path` and rotates its backups.
+    pub fn save_to(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(StorageError::from)?;
+        }
+        let doc = serde_json::to_value(self).map_err(|e| Error::Invalid(e.to_string()))?;
+        let backend = storage::open(path)?;
+        backend.save(&doc)?;
+        storage::rotate_snapshots(backend.as_ref())?;
+        Ok(())
+    }
+
+    pub fn list(&self, id: ListId) -> Option<&Tasks> {
+        self.lists.iter().find(|l| l.id == id)
+    }
+
+    pub fn list_mut(&mut self, id: ListId) -> Option<&mut Tasks> {
+        self.lists.iter_mut().find(|l| l.id == id)
+    }
+
+    fn get_mut(&mut self, id: ListId) -> Result<&mut Tasks, Error> {
+        self.list_mut(id).ok_or(Error::ListNotFound(id))
+    }
+
+    /// Number of tasks carrying each tag, across every list.
+    pub fn tag_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for task in self.lists.iter().flat_map(|l| l.list.iter()) {
+            for tag in &task.tags {
+                *counts.entry(tag.as_str()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Starts keeping the undoing change of every operation, for the
+    /// window's undo history.
+    pub fn record_changes(&mut self) {
+        self.journal.get_or_insert_with(Vec::new);
+    }
+
+    /// The undoing changes recorded since the last call, in the order the
+    /// operations ran.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.journal.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    fn record(&mut self, change: Change) {
+        if let Some(journal) = &mut self.journal {
+            journal.push(change);
+        }
+    }
+
+    /// Applies `change`, returning the change that reverts it. Nothing is
+    /// recorded; undo and redo keep their own entries.
+    pub fn apply_change(&mut self, change: Change) -> Result<Change, Error> {
+        Ok(match change {
+            Change::InsertList { position, list } => {
+                let id = list.id;
+                self.lists.insert(position.min(self.lists.len()), list);
+                Change::RemoveList { id }
+            }
+            Change::RemoveList { id } => {
+                let position = self.lists.iter().position(|l| l.id == id).ok_or(Error::ListNotFound(id))?;
+                Change::InsertList { position, list: self.lists.remove(position) }
+            }
+            Change::RenameList { id, title } => {
+                let title = std::mem::replace(&mut self.get_mut(id)?.title, title);
+                Change::RenameList { id, title }
+            }
+            Change::SetSortOrder { list, order } => {
+                let order = std::mem::replace(&mut self.get_mut(list)?.sort_order, order);
+                Change::SetSortOrder { list, order }
+            }
+            Change::InsertTask { list, position, task } => {
+                let tasks = self.get_mut(list)?;
+                let id = task.id;
+                tasks.list.insert(position.min(tasks.list.len()), *task);
+                Change::RemoveTask { list, id }
+            }
+            Change::RemoveTask { list, id } => {
+                let tasks = self.get_mut(list)?;
+                let position = tasks.list.iter().position(|t| t.id == id).ok_or(Error::TaskNotFound(id))?;
+                Change::InsertTask { list, position, task: Box::new(tasks.list.remove(position)) }
+            }
+            Change::RenameTask { list, id, title } => {
+                let title = std::mem::replace(&mut self.get_mut(list)?.get_mut(id)?.title, title);
+                Change::RenameTask { list, id, title }
+            }
+            Change::SetPriority { list, id, priority } => {
+                let priority = std::mem::replace(&mut self.get_mut(list)?.get_mut(id)?.priority, priority);
+                Change::SetPriority { list, id, priority }
+            }
+            Change::SpliceNotes { list, id, at, remove, insert } => {
+                let task = self.get_mut(list)?.get_mut(id)?;
+                let mut notes = task.notes.take().unwrap_or_default();
+                let removed = notes.get(at..at + remove).map(str::to_string);
+                let Some(removed) = removed else {
+                    task.notes = (!notes.is_empty()).then_some(notes);
+                    return Err(Error::Invalid(format!("notes of task {id} do not match the change")));
+                };
+                notes.replace_range(at..at + remove, &insert);
+                task.notes = (!notes.is_empty()).then_some(notes);
+                Change::SpliceNotes { list, id, at, remove: insert.len(), insert: removed }
+            }
+            Change::ReplaceTask { list, task } => {
+                let slot = self.get_mut(list)?.get_mut(task.id)?;
+                Change::ReplaceTask { list, task: Box::new(std::mem::replace(slot, *task)) }
+            }
+            Change::SetTheme { theme } => {
+                Change::SetTheme { theme: std::mem::replace(&mut self.selected_theme, theme) }
+            }
+            Change::InsertTrash { position, item } => {
+                let id = item.id();
+                self.trash.insert(position.min(self.trash.len()), item);
+                Change::RemoveTrash { id }
+            }
+            Change::RemoveTrash { id } => {
+                let position = self.trash.iter().position(|t| t.id() == id).ok_or(Error::NotInTrash(id))?;
+                Change::InsertTrash { position, item: self.trash.remove(position) }
+            }
+            Change::ReplaceLists { lists } => {
+                Change::ReplaceLists { lists: std::mem::replace(&mut self.lists, lists) }
+            }
+            Change::ReplaceTrash { trash } => {
+                Change::ReplaceTrash { trash: std::mem::replace(&mut self.trash, trash) }
+            }
+        })
+    }
+
+    /// Runs `edit` on the tasks of `list` and records how to put task `id`
+    /// back as it was, and to take out any next occurrence the edit added.
+    fn edit_task<T>(&mut self, list: ListId, id: TaskId, edit: impl FnOnce(&mut Tasks) -> Result<T, Error>) -> Result<T, Error> {
+        let tasks = self.get_mut(list)?;
+        let before = tasks.task(id).cloned().ok_or(Error::TaskNotFound(id))?;
+        let len = tasks.list.len();
+        let result = edit(tasks)?;
+        let added: Vec<TaskId> = tasks.list[len..].iter().map(|t| t.id).collect();
+        if tasks.task(id) != Some(&before) {
+            self.record(Change::ReplaceTask { list, task: Box::new(before) });
+        }
+        for id in added {
+            self.record(Change::RemoveTask { list, id });
+        }
+        Ok(result)
+    }
+
+    /// Appends a new, empty list.
+    pub fn add_list(&mut self, new_title: &str) -> Result<ListId, Error> {
+        self.insert_list(self.lists.len(), new_title)
+    }
+
+    /// Inserts a new, empty list at `index`, or at the end if `index` is past it.
+    pub fn insert_list(&mut self, index: usize, new_title: &str) -> Result<ListId, Error> {
+        let list = Tasks { title: title(new_title)?, ..Tasks::default() };
+        let id = list.id;
+        self.lists.insert(index.min(self.lists.len()), list);
+        self.record(Change::RemoveList { id });
+        Ok(id)
+    }
+
+    pub fn rename_list(&mut self, id: ListId, new_title: &str) -> Result<(), Error> {
+        let new_title = title(new_title)?;
+        let slot = &mut self.get_mut(id)?.title;
+        if *slot != new_title {
+            let title = std::mem::replace(slot, new_title);
+            self.record(Change::RenameList { id, title });
+        }
+        Ok(())
+    }
+
+    /// Moves a list and its tasks to the trash.
+    pub fn remove_list(&mut self, id: ListId) -> Result<(), Error> {
+        let position = self.lists.iter().position(|l| l.id == id).ok_or(Error::ListNotFound(id))?;
+        let list = self.lists.remove(position);
+        self.record(Change::InsertList { position, list: list.clone() });
+        self.move_to_trash(Trashed::List { list, position });
+        Ok(())
+    }
+
+    pub fn add_task(&mut self, list: ListId, new_title: &str) -> Result<TaskId, Error> {
+        self.push_task(list, Task::new(new_title.to_string()))
+    }
+
+    /// Appends `task` as given, e.g. with a due date already set.
+    pub fn push_task(&mut self, list: ListId, task: Task) -> Result<TaskId, Error> {
+        let id = self.get_mut(list)?.add(task)?;
+        self.record(Change::RemoveTask { list, id });
+        Ok(id)
+    }
+
+    pub fn rename_task(&mut self, list: ListId, task: TaskId, new_title: &str) -> Result<(), Error> {
+        let new_title = title(new_title)?;
+        let slot = &mut self.get_mut(list)?.get_mut(task)?.title;
+        if *slot != new_title {
+            let title = std::mem::replace(slot, new_title);
+            self.record(Change::RenameTask { list, id: task, title });
+        }
+        Ok(())
+    }
+
+    /// See `Tasks::set_status`.
+    pub fn set_status(&mut self, list: ListId, task: TaskId, status: Status) -> Result<Option<TaskId>, Error> {
+        self.edit_task(list, task, |tasks| tasks.set_status(task, status))
+    }
+
+    /// See `Tasks::advance`.
+    pub fn advance_task(&mut self, list: ListId, task: TaskId) -> Result<Option<TaskId>, Error> {
+        self.edit_task(list, task, |tasks| tasks.advance(task))
+    }
+
+    pub fn set_priority(&mut self, list: ListId, task: TaskId, priority: Priority) -> Result<(), Error> {
+        let slot = &mut self.get_mut(list)?.get_mut(task)?.priority;
+        if *slot != priority {
+            let priority = std::mem::replace(slot, priority);
+            self.record(Change::SetPriority { list, id: task, priority });
+        }
+        Ok(())
+    }
+
+    /// Sets or clears the due date. A due time is only kept along with a date.
+    pub fn set_due(&mut self, list: ListId, task: TaskId, date: Option<NaiveDate>, time: Option<NaiveTime>) -> Result<(), Error> {
+        self.edit_task(list, task, |tasks| {
+            let task = tasks.get_mut(task)?;
+            task.due_date = date;
+            task.due_time = date.and(time);
+            Ok(())
+        })
+    }
+
+    pub fn set_tags(&mut self, list: ListId, task: TaskId, tags: Vec<String>) -> Result<(), Error> {
+        self.edit_task(list, task, |tasks| {
+            tasks.get_mut(task)?.tags = tags;
+            Ok(())
+        })
+    }
+
+    pub fn set_recurrence(&mut self, list: ListId, task: TaskId, recurrence: Option<Recurrence>) -> Result<(), Error> {
+        self.edit_task(list, task, |tasks| {
+            tasks.get_mut(task)?.recurrence = recurrence;
+            Ok(())
+        })
+    }
+
+    /// Replaces a task's notes; blank notes are removed.
+    pub fn set_notes(&mut self, list: ListId, task: TaskId, notes: &str) -> Result<(), Error> {
+        let slot = &mut self.get_mut(list)?.get_mut(task)?.notes;
+        let before = std::mem::replace(slot, (!notes.trim().is_empty()).then(|| notes.to_string()));
+        let (before, after) = (before.unwrap_or_default(), slot.as_deref().unwrap_or_default());
+        if before != after {
+            let (at, remove, insert) = splice_between(after, &before);
+            self.record(Change::SpliceNotes { list, id: task, at, remove, insert });
+        }
+        Ok(())
+    }
+
+    /// Shows or hides a task's subtasks.
+    pub fn toggle_collapsed(&mut self, list: ListId, task: TaskId) -> Result<(), Error> {
+        self.edit_task(list, task, |tasks| {
+            let task = tasks.get_mut(task)?;
+            task.collapsed = !task.collapsed;
+            Ok(())
+        })
+    }
+
+    pub fn set_sort_order(&mut self, list: ListId, order: SortOrder) -> Result<(), Error> {
+        let slot = &mut self.get_mut(list)?.sort_order;
+        if *slot != order {
+            let order = std::mem::replace(slot, order);
+            self.record(Change::SetSortOrder { list, order });
+        }
+        Ok(())
+    }
+
+    /// Moves a task to the trash.
+    pub fn remove_task(&mut self, list: ListId, task: TaskId) -> Result<(), Error> {
+        let item = self.get_mut(list)?.remove(task)?;
+        if let Trashed::Task { task, position, .. } = &item {
+            self.record(Change::InsertTask { list, position: *position, task: task.clone() });
+        }
+        self.move_to_trash(item);
+        Ok(())
+    }
+
+    /// See `Tasks::add_subtask`.
+    pub fn add_subtask(&mut self, list: ListId, task: TaskId, new_title: &str) -> Result<TaskId, Error> {
+        self.edit_task(list, task, |tasks| tasks.add_subtask(task, new_title))
+    }
+
+    /// See `Tasks::advance_subtask`.
+    pub fn advance_subtask(&mut self, list: ListId, task: TaskId, sub: TaskId) -> Result<Option<TaskId>, Error> {
+        self.edit_task(list, task, |tasks| tasks.advance_subtask(task, sub))
+    }
+
+    /// Moves a subtask to the trash.
+    pub fn remove_subtask(&mut self, list: ListId, task: TaskId, sub: TaskId) -> Result<(), Error> {
+        let item = self.edit_task(list, task, |tasks| tasks.remove_subtask(task, sub))?;
+        self.move_to_trash(item);
+        Ok(())
+    }
+
+    pub fn set_theme(&mut self, theme: Themes) {
+        let theme = self.selected_theme.replace(theme);
+        self.record(Change::SetTheme { theme });
+    }
+
+    /// Replaces every list, as when restoring a backup. The trash is kept.
+    pub fn replace_lists(&mut self, lists: Vec<Tasks>) {
+        let lists = std::mem::replace(&mut self.lists, lists);
+        self.record(Change::ReplaceLists { lists });
+    }
+
+    /// Replaces the lists, trash and selected theme with another copy of
+    /// the document, as when reloading it after an outside edit.
+    pub fn replace_content(&mut self, lists: Vec<Tasks>, trash: Vec<TrashItem>, theme: Option<Themes>) {
+        self.replace_lists(lists);
+        let trash = std::mem::replace(&mut self.trash, trash);
+        self.record(Change::ReplaceTrash { trash });
+        let theme = std::mem::replace(&mut self.selected_theme, theme);
+        self.record(Change::SetTheme { theme });
+    }
+
+    /// Merges another copy of the document into this one, as described in
+    /// `merge_lists`, with `base` as the version both started from. Their
+    /// trash is merged the same way, leaving out what this document still
+    /// has in its lists.
+    pub fn merge(&mut self, base: &List, theirs: List) {
+        let mut lists = self.lists.clone();
+        merge_lists(&base.lists, &mut lists, theirs.lists);
+
+        let in_base = |id: Uuid| base.trash.iter().any(|item| item.id() == id);
+        let mut trash = self.trash.clone();
+        // Restored or emptied on their side.
+        trash.retain(|item| theirs.trash.iter().any(|t| t.id() == item.id()) || !in_base(item.id()));
+        for item in theirs.trash {
+            let id = item.id();
+            if !in_base(id) && !trash.iter().any(|t| t.id() == id) && !contains_id(&lists, id) {
+                trash.push(item);
+            }
+        }
+
+        let theme = if self.selected_theme == base.selected_theme {
+            theirs.selected_theme
+        } else {
+            self.selected_theme
+        };
+        self.replace_content(lists, trash, theme);
+    }
+
+    pub fn move_to_trash(&mut self, item: Trashed) {
+        let item = TrashItem { deleted_at: Local::now(), item };
+        let id = item.id();
+        self.trash.push(item);
+        self.record(Change::RemoveTrash { id });
+    }
+
+    /// Permanently deletes trash items older than `days`; zero keeps
+    /// everything. Returns whether anything was removed.
+    pub fn purge_trash(&mut self, days: u32) -> bool {
+        if days == 0 {
+            return false;
+        }
+        let cutoff = Local::now() - chrono::Duration::days(days.into());
+        let mut purged = false;
+        let mut position = 0;
+        while position < self.trash.len() {
+            if self.trash[position].deleted_at < cutoff {
+                let item = self.trash.remove(position);
+                self.record(Change::InsertTrash { position, item });
+                purged = true;
+            } else {
+                position += 1;
+            }
+        }
+        purged
+    }
+
+    /// Deletes an item from the trash for good.
+    pub fn delete_from_trash(&mut self, id: Uuid) -> Result<(), Error> {
+        let position = self.trash.iter().position(|t| t.id() == id).ok_or(Error::NotInTrash(id))?;
+        let item = self.trash.remove(position);
+        self.record(Change::InsertTrash { position, item });
+        Ok(())
+    }
+
+    /// Deletes everything in the trash. Returns whether it held anything.
+    pub fn empty_trash(&mut self) -> bool {
+        if self.trash.is_empty() {
+            return false;
+        }
+        let trash = std::mem::take(&mut self.trash);
+        self.record(Change::ReplaceTrash { trash });
+        true
+    }
+
+    /// Puts a trashed item back where it came from. A task whose list was
+    /// deleted as well brings the list back with it.
+    pub fn restore_from_trash(&mut self, id: Uuid) -> Result<(), Error> {
+        let index = self.trash.iter().position(|t| t.id() == id).ok_or(Error::NotInTrash(id))?;
+        let item = self.trash.remove(index);
+        self.record(Change::InsertTrash { position: index, item: item.clone() });
+        match item.item {
+            Trashed::List { list, position } => {
+                let at = position.min(self.lists.len());
+                self.lists.insert(at, list);
+                self.record(Change::RemoveList { id });
+            }
+            Trashed::Task { task, list, list_title, parent, position } => {
+                if self.list(list).is_none() {
+                    let _ = self.restore_from_trash(list);
+                }
+                if self.list(list).is_none() {
+                    self.lists.push(Tasks { id: list, title: list_title, ..Tasks::default() });
+                    self.record(Change::RemoveList { id: list });
+                }
+                let target = self.get_mut(list)?;
+                match parent.filter(|p| target.task(*p).is_some()) {
+                    Some(parent) => {
+                        self.edit_task(list, parent, |tasks| {
+                            let parent = tasks.get_mut(parent)?;
+                            let at = position.min(parent.subtasks.len());
+                            parent.subtasks.insert(at, *task);
+                            parent.sync_with_subtasks();
+                            Ok(())
+                        })?;
+                    }
+                    None => {
+                        let at = position.min(target.list.len());
+                        target.list.insert(at, *task);
+                        self.record(Change::RemoveTask { list, id });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The splice that turns `from` into `to`: the byte offset where they
+/// differ, how many bytes of `from` to replace there, and with what.
+fn splice_between(from: &str, to: &str) -> (usize, usize, String) {
+    let prefix = from.char_indices()
+        .zip(to.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(from.len().min(to.len()), |((i, _), _)| i);
+    let prefix = (0..=prefix).rev().find(|&i| to.is_char_boundary(i) && from.is_char_boundary(i)).unwrap_or(0);
+    let (from_rest, to_rest) = (&from[prefix..], &to[prefix..]);
+    let suffix = from_rest.chars().rev()
+        .zip(to_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    let remove = from_rest.len() - suffix;
+    (prefix, remove, to_rest[..to_rest.len() - suffix].to_string())
+}
+
+impl Change {
+    /// Whether this changes the lists themselves rather than tasks in them
+    /// or the document's other fields.
+    pub fn touches_lists(&self) -> bool {
+        matches!(
+            self,
+            Change::InsertList { .. }
+                | Change::RemoveList { .. }
+                | Change::RenameList { .. }
+                | Change::SetSortOrder { .. }
+                | Change::ReplaceLists { .. }
+        )
+    }
+
+    /// The task this changes, with its list.
+    pub fn task(&self) -> Option<(ListId, TaskId)> {
+        match self {
+            Change::InsertTask { list, task, .. } | Change::ReplaceTask { list, task } => Some((*list, task.id)),
+            Change::RemoveTask { list, id }
+            | Change::RenameTask { list, id, .. }
+            | Change::SetPriority { list, id, .. }
+            | Change::SpliceNotes { list, id, .. } => Some((*list, *id)),
+            _ => None,
+        }
+    }
+
+    /// Folds `newer`, recorded right after this change, into it when both
+    /// undo typing in the same notes, so that the pair is undone in one step.
+    /// `notes` is the task's text now, after the edit `newer` undoes.
+    pub fn absorb(&mut self, newer: &Change, notes: &str) -> bool {
+        let (
+            Change::SpliceNotes { list, id, at: older_at, remove: older_remove, insert: older_insert },
+            Change::SpliceNotes { id: newer_id, at, remove, insert, .. },
+        ) = (&*self, newer) else {
+            return false;
+        };
+        if id != newer_id {
+            return false;
+        }
+        let mut text = notes.to_string();
+        for (at, remove, insert) in [(*at, *remove, insert), (*older_at, *older_remove, older_insert)] {
+            if text.get(at..at + remove).is_none() {
+                return false;
+            }
+            text.replace_range(at..at + remove, insert);
+        }
+        let (at, remove, insert) = splice_between(notes, &text);
+        *self = Change::SpliceNotes { list: *list, id: *id, at, remove, insert };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_keeps_both_sides_and_prefers_mine() {
+        let shared = Task::new(String::from("Milk"));
+        let mine_only = Task::new(String::from("Eggs"));
+        let theirs_only = Task::new(String::from("Bread"));
+        let groceries = Tasks { title: String::from("Groceries"), list: vec![shared.clone(), mine_only], ..Tasks::default() };
+
+        let mut mine = vec![groceries];
+        let mut their_groceries = Tasks { id: mine[0].id, title: String::from("Groceries"), ..Tasks::default() };
+        their_groceries.list = vec![Task { title: String::from("Oat milk"), ..shared }, theirs_only];
+        let chores = Tasks { title: String::from("Chores"), ..Tasks::default() };
+        let chores_id = chores.id;
+
+        merge_lists(&[], &mut mine, vec![their_groceries, chores]);
+
+        let titles: Vec<&str> = mine[0].list.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["Milk", "Eggs", "Bread"]);
+        assert_eq!(mine[1].id, chores_id);
+    }
+
+    /// A document with one list holding "Milk" and "Eggs", as both sides
+    /// last saw it.
+    fn merge_base() -> (List, ListId, TaskId, TaskId) {
+        let mut doc = List::new();
+        let list = doc.add_list("Groceries").unwrap();
+        let milk = doc.add_task(list, "Milk").unwrap();
+        let eggs = doc.add_task(list, "Eggs").unwrap();
+        (doc, list, milk, eggs)
+    }
+
+    fn copy(doc: &List) -> List {
+        List { lists: doc.lists.clone(), trash: doc.trash.clone(), ..List::new() }
+    }
+
+    fn titles(doc: &List) -> Vec<&str> {
+        doc.lists[0].list.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn merge_applies_their_edits_unless_mine_conflict() {
+        let (base, list, milk, eggs) = merge_base();
+        let mut mine = copy(&base);
+        mine.rename_task(list, milk, "Oat milk").unwrap();
+        mine.set_priority(list, eggs, Priority::High).unwrap();
+        let mut theirs = copy(&base);
+        theirs.rename_task(list, milk, "Soy milk").unwrap();
+        theirs.rename_task(list, eggs, "Free-range eggs").unwrap();
+
+        mine.merge(&base, theirs);
+
+        assert_eq!(titles(&mine), ["Oat milk", "Free-range eggs"]);
+        assert_eq!(mine.lists[0].list[1].priority, Priority::High);
+    }
+
+    #[test]
+    fn merge_keeps_a_deletion_on_their_side() {
+        let (base, list, milk, _) = merge_base();
+        let mut mine = copy(&base);
+        mine.add_task(list, "Bread").unwrap();
+        let mut theirs = copy(&base);
+        theirs.remove_task(list, milk).unwrap();
+
+        mine.merge(&base, theirs);
+
+        assert_eq!(titles(&mine), ["Eggs", "Bread"]);
+        assert_eq!(mine.trash.len(), 1, "their trashed task is not duplicated");
+    }
+
+    #[test]
+    fn merge_keeps_a_deletion_on_my_side() {
+        let (base, list, milk, eggs) = merge_base();
+        let mut mine = copy(&base);
+        mine.remove_task(list, milk).unwrap();
+        let mut theirs = copy(&base);
+        theirs.rename_task(list, eggs, "Free-range eggs").unwrap();
+
+        mine.merge(&base, theirs);
+
+        assert_eq!(titles(&mine), ["Free-range eggs"]);
+        assert_eq!(mine.trash.len(), 1);
+    }
+
+    #[test]
+    fn merge_keeps_a_task_i_edited_that_they_deleted() {
+        let (base, list, milk, _) = merge_base();
+        let mut mine = copy(&base);
+        mine.rename_task(list, milk, "Oat milk").unwrap();
+        let mut theirs = copy(&base);
+        theirs.remove_task(list, milk).unwrap();
+
+        mine.merge(&base, theirs);
+
+        assert_eq!(titles(&mine), ["Oat milk", "Eggs"]);
+        assert!(mine.trash.is_empty(), "the task is not also in the trash");
+    }
+
+    #[test]
+    fn ids_given_to_an_old_file_survive_reopening() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.json");
+        fs::write(&path, r#"{ "lists": [{ "title": "Chores", "list": [{ "title": "Sweep", "status": "Pending" }] }], "themes": [] }"#).unwrap();
+
+        let first = List::open(&path).unwrap();
+        let second = List::open(&path).unwrap();
+
+        assert_eq!(first.lists[0].id, second.lists[0].id);
+        assert_eq!(first.lists[0].list[0].id, second.lists[0].list[0].id);
+        assert_ne!(Task::default().id, Task::default().id);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recorded_changes_undo_their_operations() {
+        let mut doc = List::new();
+        let list = doc.add_list("Notes").unwrap();
+        let id = doc.add_task(list, "Trip").unwrap();
+        doc.set_notes(list, id, "naïve café").unwrap();
+        doc.record_changes();
+
+        doc.set_notes(list, id, "naïf café au lait").unwrap();
+        doc.advance_task(list, id).unwrap();
+        doc.remove_task(list, id).unwrap();
+        let changes = doc.take_changes();
+        assert_eq!(changes.len(), 4);
+        for change in changes.into_iter().rev() {
+            doc.apply_change(change).unwrap();
+        }
+
+        assert!(doc.trash.is_empty());
+        let task = doc.list(list).unwrap().task(id).unwrap();
+        assert_eq!(task.status, Status::Pending);
+        assert_eq!(task.notes.as_deref(), Some("naïve café"));
+    }
+
+    #[test]
+    fn completing_a_recurring_task_adds_its_next_occurrence() {
+        let mut app = List::new();
+        let list = app.add_list("Chores").unwrap();
+        let id = app.add_task(list, "Water plants").unwrap();
+        let today = Local::now().date_naive();
+        let task = app.list_mut(list).unwrap().task_mut(id).unwrap();
+        task.due_date = Some(today);
+        task.recurrence = Some(Recurrence::Daily);
+
+        let next = app.set_status(list, id, Status::Complete).unwrap().expect("next occurrence");
+
+        let chores = app.list(list).unwrap();
+        assert_eq!(chores.task(id).unwrap().recurrence, None);
+        let next = chores.task(next).unwrap();
+        assert_eq!(next.status, Status::Pending);
+        assert_eq!(next.due_date, Some(today + Days::new(1)));
+        assert_eq!(next.recurrence, Some(Recurrence::Daily));
+    }
+
+    #[test]
+    fn due_times_earlier_today_are_overdue_and_completed_tasks_never_are() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let now = today.and_hms_opt(12, 0, 0).unwrap();
+        let due = |time: Option<NaiveTime>| Task { due_date: Some(today), due_time: time, ..Task::new(String::from("Report")) };
+
+        let this_morning = due(NaiveTime::from_hms_opt(9, 0, 0));
+        assert!(this_morning.is_overdue(now));
+        assert!(!this_morning.is_due_today(now));
+
+        let tonight = due(NaiveTime::from_hms_opt(18, 0, 0));
+        assert!(!tonight.is_overdue(now));
+        assert!(tonight.is_due_today(now));
+
+        let any_time = due(None);
+        assert!(!any_time.is_overdue(now));
+        assert!(any_time.is_due_today(now));
+
+        let done = Task { status: Status::Complete, ..this_morning };
+        assert!(!done.is_overdue(now));
+        assert!(!done.is_due_today(now));
+        let done_today = Task { status: Status::Complete, ..any_time };
+        assert!(!done_today.is_due_today(now));
+    }
+
+    #[test]
+    fn monthly_clamps_to_short_months_and_weekly_wraps_into_next_week() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let monthly = Recurrence::Monthly(31);
+        assert_eq!(monthly.next_after(date(2024, 1, 31)), date(2024, 2, 29));
+        assert_eq!(monthly.next_after(date(2023, 1, 31)), date(2023, 2, 28));
+        assert_eq!(monthly.next_after(date(2024, 2, 29)), date(2024, 3, 31));
+        assert_eq!(monthly.next_after(date(2024, 4, 10)), date(2024, 4, 30));
+        assert_eq!(monthly.next_after(date(2024, 12, 31)), date(2025, 1, 31));
+
+        // 2024-03-15 is a Friday.
+        let weekly = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Wed]);
+        assert_eq!(weekly.next_after(date(2024, 3, 15)), date(2024, 3, 18));
+        assert_eq!(weekly.next_after(date(2024, 3, 18)), date(2024, 3, 20));
+        assert_eq!(weekly.next_after(date(2024, 3, 20)), date(2024, 3, 25));
+        let fridays = Recurrence::Weekly(vec![Weekday::Fri]);
+        assert_eq!(fridays.next_after(date(2024, 3, 15)), date(2024, 3, 22));
+    }
+
+    #[test]
+    fn removed_tasks_are_restored_in_place() {
+        let mut app = List::new();
+        let list = app.add_list("Groceries").unwrap();
+        let milk = app.add_task(list, "Milk").unwrap();
+        let eggs = app.add_task(list, "Eggs").unwrap();
+
+        app.remove_task(list, milk).unwrap();
+        assert_eq!(app.trash.len(), 1);
+        app.restore_from_trash(milk).unwrap();
+
+        let ids: Vec<TaskId> = app.list(list).unwrap().list.iter().map(|t| t.id).collect();
+        assert_eq!(ids, [milk, eggs]);
+        assert!(app.trash.is_empty());
+    }
+
+    #[test]
+    fn operations_reject_blank_titles_and_unknown_ids() {
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let missing = Uuid::new_v4();
+
+        assert!(matches!(app.add_list("  "), Err(Error::EmptyTitle)));
+        assert!(matches!(app.add_task(list, ""), Err(Error::EmptyTitle)));
+        assert!(matches!(app.add_task(missing, "Report"), Err(Error::ListNotFound(_))));
+        assert!(matches!(app.remove_task(list, missing), Err(Error::TaskNotFound(_))));
+        assert!(matches!(app.restore_from_trash(missing), Err(Error::NotInTrash(_))));
+        assert_eq!(app.list(list).unwrap().list.len(), 0);
+    }
+
+    #[test]
+    fn due_dates_parse_strictly_and_a_time_needs_a_date() {
+        assert_eq!(parse_due_date(" 2024-02-29 "), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(parse_due_date("2023-02-29"), None);
+        assert_eq!(parse_due_date("tomorrow"), None);
+        assert_eq!(parse_due_time("09:30"), NaiveTime::from_hms_opt(9, 30, 0));
+        assert_eq!(parse_due_time("25:00"), None);
+
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let id = app.add_task(list, "Report").unwrap();
+        app.set_due(list, id, None, NaiveTime::from_hms_opt(9, 30, 0)).unwrap();
+        let task = app.list(list).unwrap().task(id).unwrap();
+        assert_eq!((task.due_date, task.due_time), (None, None));
+    }
+
+    #[test]
+    fn priority_order_puts_urgent_first_and_keeps_ties_in_place() {
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let ids: Vec<TaskId> = ["A", "B", "C", "D"].iter().map(|t| app.add_task(list, t).unwrap()).collect();
+        app.set_priority(list, ids[1], Priority::Urgent).unwrap();
+        app.set_priority(list, ids[2], Priority::Low).unwrap();
+        app.set_priority(list, ids[3], Priority::Urgent).unwrap();
+
+        let tasks = app.list(list).unwrap();
+        assert_eq!(tasks.display_order(&[]), [0, 1, 2, 3]);
+        app.set_sort_order(list, SortOrder::Priority).unwrap();
+        let tasks = app.list(list).unwrap();
+        assert_eq!(tasks.display_order(&[]), [1, 3, 2, 0]);
+        let titles: Vec<&str> = tasks.list.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["A", "B", "C", "D"]);
+    }
+
+    #[test]
+    fn tags_parse_without_duplicates_and_filters_need_every_tag() {
+        assert_eq!(parse_tags("#backend, ops  #backend"), ["backend", "ops"]);
+        assert!(parse_tags(" , # ").is_empty());
+
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let ids: Vec<TaskId> = ["A", "B", "C"].iter().map(|t| app.add_task(list, t).unwrap()).collect();
+        app.set_tags(list, ids[0], parse_tags("ops backend")).unwrap();
+        app.set_tags(list, ids[1], parse_tags("ops")).unwrap();
+
+        let tasks = app.list(list).unwrap();
+        assert_eq!(tasks.tags(), ["backend", "ops"]);
+        assert_eq!(tasks.display_order(&[String::from("ops")]), [0, 1]);
+        assert_eq!(tasks.display_order(&[String::from("ops"), String::from("backend")]), [0]);
+        assert!(tasks.display_order(&[String::from("frontend")]).is_empty());
+    }
+
+    #[test]
+    fn notes_keep_their_lines_and_blank_notes_are_removed() {
+        let mut app = List::new();
+        let list = app.add_list("Trip").unwrap();
+        let id = app.add_task(list, "Pack").unwrap();
+
+        app.set_notes(list, id, "passport\n  charger\n").unwrap();
+        assert_eq!(app.list(list).unwrap().task(id).unwrap().notes.as_deref(), Some("passport\n  charger\n"));
+        app.set_notes(list, id, " \n\t").unwrap();
+        assert_eq!(app.list(list).unwrap().task(id).unwrap().notes, None);
+        assert!(matches!(app.set_notes(list, Uuid::new_v4(), "x"), Err(Error::TaskNotFound(_))));
+    }
+
+    #[test]
+    fn subtasks_count_towards_progress_and_complete_their_parent() {
+        let mut app = List::new();
+        let list = app.add_list("Trip").unwrap();
+        let id = app.add_task(list, "Pack").unwrap();
+        let subs: Vec<TaskId> = ["Passport", "Charger"].iter().map(|t| app.add_subtask(list, id, t).unwrap()).collect();
+        let task = |app: &List| app.list(list).unwrap().task(id).unwrap().clone();
+        assert_eq!(task(&app).progress(), (0, 2));
+
+        app.advance_subtask(list, id, subs[0]).unwrap();
+        app.advance_subtask(list, id, subs[0]).unwrap();
+        assert_eq!(task(&app).progress(), (1, 2));
+        assert_eq!(task(&app).status, Status::Pending);
+
+        app.advance_subtask(list, id, subs[1]).unwrap();
+        app.advance_subtask(list, id, subs[1]).unwrap();
+        assert_eq!(task(&app).status, Status::Complete);
+
+        app.advance_subtask(list, id, subs[1]).unwrap();
+        assert_eq!(task(&app).status, Status::InProgress);
+        app.remove_subtask(list, id, subs[1]).unwrap();
+        assert_eq!(task(&app).progress(), (1, 1));
+        assert_eq!(task(&app).status, Status::Complete);
+        assert!(matches!(app.add_subtask(list, id, " "), Err(Error::EmptyTitle)));
+    }
+
+    #[test]
+    fn repeat_rules_round_trip_and_the_next_occurrence_skips_the_past() {
+        for rule in ["daily", "weekly mon,fri", "weekly", "monthly 15", "every 3 days"] {
+            assert_eq!(parse_recurrence(rule).unwrap().to_string(), rule);
+        }
+        assert_eq!(parse_recurrence("Weekly Mon Fri"), Some(Recurrence::Weekly(vec![Weekday::Mon, Weekday::Fri])));
+        for bad in ["", "fortnightly", "weekly someday", "monthly 32", "every 0 days"] {
+            assert_eq!(parse_recurrence(bad), None, "{bad:?}");
+        }
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut task = Task {
+            due_date: Some(date(2024, 3, 1)),
+            recurrence: Some(Recurrence::EveryNDays(7)),
+            ..Task::new(String::from("Backup"))
+        };
+        let next = task.next_occurrence(date(2024, 3, 20)).unwrap();
+        assert_eq!(next.due_date, Some(date(2024, 3, 22)));
+        assert_ne!(next.id, task.id);
+        assert_eq!(task.recurrence, None);
+        assert!(task.next_occurrence(date(2024, 3, 20)).is_none());
+    }
+
+    #[test]
+    fn status_changes_are_stamped_and_logged() {
+        let mut task = Task::new(String::from("Report"));
+        assert!(task.created_at.is_some());
+        assert!(task.started_at.is_none() && task.completed_at.is_none());
+
+        task.advance();
+        let started = task.started_at.expect("started");
+        task.advance();
+        assert!(task.completed_at.is_some());
+        task.advance();
+        assert_eq!(task.status, Status::InProgress);
+        assert_eq!(task.started_at, Some(started));
+        assert!(task.completed_at.is_none());
+
+        task.set_status(Status::InProgress);
+        let steps: Vec<(Status, Status)> = task.history.iter().map(|c| (c.from, c.to)).collect();
+        assert_eq!(steps, [
+            (Status::Pending, Status::InProgress),
+            (Status::InProgress, Status::Complete),
+            (Status::Complete, Status::InProgress),
+        ]);
+    }
+
+    #[test]
+    fn every_task_gets_its_own_id() {
+        assert_ne!(Task::default().id, Task::default().id);
+        assert_ne!(Tasks::default().id, Tasks::default().id);
+        let read: Vec<Task> = serde_json::from_value(serde_json::json!([
+            { "title": "A", "status": "Pending", "subtasks": [{ "title": "A1", "status": "Pending" }] },
+            { "title": "B", "status": "Pending" },
+        ])).unwrap();
+        assert_ne!(read[0].id, read[1].id);
+        assert_ne!(read[0].id, read[0].subtasks[0].id);
+
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let a = app.add_task(list, "A").unwrap();
+        let b = app.add_task(list, "B").unwrap();
+        app.set_priority(list, b, Priority::High).unwrap();
+        app.set_sort_order(list, SortOrder::Priority).unwrap();
+        app.remove_task(list, a).unwrap();
+        assert_eq!(app.list(list).unwrap().list.iter().map(|t| t.id).collect::<Vec<_>>(), [b]);
+    }
+}
@@ -1,5 +1,15 @@
 #![allow(non_snake_case)]
 pub mod models;
+pub mod core;
 pub mod app;
+pub mod cli;
+pub mod config;
+pub mod storage;
+pub mod migrations;
+pub mod server;
+#[cfg(unix)]
+pub mod rpc;
+#[cfg(feature = "tui")]
+pub mod tui;
 
 pub use models::*;
@@ -1,15 +1,74 @@
 #![cfg_attr(all(not(debug_assertions), target_os = "windows"), windows_subsystem = "windows")]
 
-use iced;
+#[cfg(feature = "gui")]
 use iced::Size;
 
+use TaskMaster::{cli, config, server};
+#[cfg(feature = "gui")]
 use TaskMaster::models::*;
 
-fn main() -> iced::Result {
-    iced::application("TaskMaster", List::update, List::view)
-        .theme(|s: &List| s.app_theme())
+fn main() {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Some(dir) = args.data_dir {
+        config::set_data_dir_flag(dir);
+    }
+    if let Some(name) = args.workspace {
+        config::set_workspace_flag(name);
+    }
+    let (command, json) = (args.command, args.json);
+
+    if let Some(port) = args.serve {
+        if let Err(e) = server::run(port.unwrap_or(server::DEFAULT_PORT)) {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if command.first().is_some_and(|name| name == "tui") {
+        #[cfg(feature = "tui")]
+        let result = TaskMaster::tui::run().map_err(|e| e.to_string());
+        #[cfg(not(feature = "tui"))]
+        let result: Result<(), &str> = Err("this build does not include the terminal frontend (cargo feature `tui`)");
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some((name, rest)) = command.split_first() {
+        if let Err(e) = cli::run(name, rest, json) {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(feature = "gui")]
+    let result = window().map_err(|e| e.to_string());
+    #[cfg(not(feature = "gui"))]
+    let result: Result<(), String> = Err(format!("this build has no window (cargo feature `gui`)\n\n{}", cli::USAGE));
+    if let Err(e) = result {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn window() -> iced::Result {
+    iced::application("TaskMaster", App::window_update, App::view)
+        .theme(|s: &App| s.app_theme())
+        .subscription(App::subscription)
+        .exit_on_close_request(false)
         .window_size(Size::new(1000.0, 700.0))
         .centered()
         .resizable(false)
         .run()
-}
\ No newline at end of file
+}
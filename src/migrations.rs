@@ -0,0 +1,187 @@
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Version written by this build. Bump it together with a new entry in
+/// `MIGRATIONS` whenever the on-disk shape of `List` changes.
+pub const CURRENT_VERSION: u64 = 3;
+
+/// `MIGRATIONS[n]` upgrades a document from version `n` to `n + 1`.
+const MIGRATIONS: [fn(Value) -> Value; CURRENT_VERSION as usize] = [
+    wrap_single_list,
+    assign_ids,
+    add_trash,
+];
+
+/// Version 0 is a legacy `todo.json` holding a single `Tasks`, version 1 is a
+/// `lists.json` from before the version field existed.
+pub fn detect_version(doc: &Value) -> u64 {
+    match doc.get("schema_version").and_then(Value::as_u64) {
+        Some(version) => version,
+        None if doc.get("lists").is_some() => 1,
+        None => 0,
+    }
+}
+
+/// Whether `doc` was written by an older version and changes when migrated.
+pub fn is_outdated(doc: &Value) -> bool {
+    detect_version(doc) < CURRENT_VERSION
+}
+
+/// Upgrades `doc` to `CURRENT_VERSION`, one step at a time.
+pub fn migrate(mut doc: Value) -> Result<Value, String> {
+    if !doc.is_object() {
+        return Err(String::from("expected a JSON object"));
+    }
+    let version = detect_version(&doc);
+    if version > CURRENT_VERSION {
+        return Err(format!(
+            "file has schema version {version}, but this build only understands up to {CURRENT_VERSION}"
+        ));
+    }
+    for step in &MIGRATIONS[version as usize..] {
+        doc = step(doc);
+    }
+    if let Some(obj) = doc.as_object_mut() {
+        obj.insert(String::from("schema_version"), Value::from(CURRENT_VERSION));
+    }
+    Ok(doc)
+}
+
+/// 0 -> 1: a single `Tasks` becomes the only entry of `lists`.
+fn wrap_single_list(mut tasks: Value) -> Value {
+    if let Some(obj) = tasks.as_object_mut() {
+        let untitled = obj.get("title")
+            .and_then(Value::as_str)
+            .is_none_or(|t| t.trim().is_empty());
+        if untitled {
+            obj.insert(String::from("title"), Value::from("Unnamed"));
+        }
+    }
+    let mut doc = Map::new();
+    doc.insert(String::from("lists"), Value::Array(vec![tasks]));
+    doc.insert(String::from("themes"), Value::Array(Vec::new()));
+    doc.insert(String::from("selected_theme"), Value::Null);
+    Value::Object(doc)
+}
+
+/// 1 -> 2: every list, task and subtask gets a persistent `id`.
+fn assign_ids(mut doc: Value) -> Value {
+    fn ensure_id(obj: &mut Map<String, Value>) {
+        if !obj.get("id").is_some_and(Value::is_string) {
+            obj.insert(String::from("id"), Value::from(Uuid::new_v4().to_string()));
+        }
+    }
+    fn visit_tasks(tasks: Option<&mut Value>) {
+        for task in tasks.and_then(Value::as_array_mut).into_iter().flatten() {
+            if let Some(task) = task.as_object_mut() {
+                ensure_id(task);
+                visit_tasks(task.get_mut("subtasks"));
+            }
+        }
+    }
+
+    for list in doc.get_mut("lists").and_then(Value::as_array_mut).into_iter().flatten() {
+        if let Some(list) = list.as_object_mut() {
+            ensure_id(list);
+            visit_tasks(list.get_mut("list"));
+        }
+    }
+    doc
+}
+
+/// 2 -> 3: documents gained a `trash`, which starts out empty.
+fn add_trash(mut doc: Value) -> Value {
+    if let Some(obj) = doc.as_object_mut() {
+        obj.entry("trash").or_insert_with(|| Value::Array(Vec::new()));
+    }
+    doc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::List;
+
+    const FIXTURES: [(&str, &str); 4] = [
+        ("v0_todo.json", include_str!("../tests/fixtures/v0_todo.json")),
+        ("v1_lists.json", include_str!("../tests/fixtures/v1_lists.json")),
+        ("v2_lists.json", include_str!("../tests/fixtures/v2_lists.json")),
+        ("v3_lists.json", include_str!("../tests/fixtures/v3_lists.json")),
+    ];
+
+    fn load(fixture: &str) -> List {
+        let doc = serde_json::from_str(fixture).unwrap();
+        serde_json::from_value(migrate(doc).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn detects_fixture_versions() {
+        for (i, (name, fixture)) in FIXTURES.iter().enumerate() {
+            let doc: Value = serde_json::from_str(fixture).unwrap();
+            assert_eq!(detect_version(&doc), i as u64, "{name}");
+        }
+    }
+
+    #[test]
+    fn every_fixture_migrates_to_the_same_content() {
+        let expected = load(FIXTURES[3].1);
+        let expected = &expected.lists[0];
+        for (version, (name, fixture)) in FIXTURES.into_iter().enumerate() {
+            let app = load(fixture);
+
+            assert_eq!(app.schema_version, CURRENT_VERSION, "{name}");
+            assert_eq!(app.lists.len(), 1, "{name}");
+            let list = &app.lists[0];
+            assert_eq!(list.title, "Groceries", "{name}");
+            assert_eq!(list.list.len(), expected.list.len(), "{name}");
+            for (task, want) in list.list.iter().zip(&expected.list) {
+                assert_eq!((&task.title, task.status), (&want.title, want.status), "{name}");
+                // Version 0 only knew titles and statuses.
+                if version >= 1 {
+                    assert_eq!((task.priority, &task.tags, &task.notes), (want.priority, &want.tags, &want.notes), "{name}");
+                    assert_eq!((task.due_date, task.due_time), (want.due_date, want.due_time), "{name}");
+                }
+            }
+            if version >= 2 {
+                assert_eq!(list.id, expected.id, "{name}");
+                assert!(list.list.iter().zip(&expected.list).all(|(t, want)| t.id == want.id), "{name}");
+            } else {
+                assert!(!list.id.is_nil() && list.list.iter().all(|t| !t.id.is_nil()), "{name}");
+                assert_ne!(list.list[0].id, list.list[1].id, "{name}");
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_todo_without_title_is_named() {
+        let doc = serde_json::json!({ "list": [] });
+        let app: List = serde_json::from_value(migrate(doc).unwrap()).unwrap();
+
+        assert_eq!(app.lists[0].title, "Unnamed");
+    }
+
+    #[test]
+    fn trash_survives_from_version_three() {
+        let doc: Value = serde_json::from_str(FIXTURES[2].1).unwrap();
+        assert_eq!(migrate(doc).unwrap()["trash"], serde_json::json!([]));
+        assert!(load(FIXTURES[2].1).trash.is_empty());
+        assert_eq!(load(FIXTURES[3].1).trash.len(), 1);
+    }
+
+    #[test]
+    fn current_version_keeps_stored_ids() {
+        let doc: Value = serde_json::from_str(FIXTURES[3].1).unwrap();
+        let expected = doc["lists"][0]["list"][1]["id"].clone();
+
+        let migrated = migrate(doc).unwrap();
+
+        assert_eq!(migrated["lists"][0]["list"][1]["id"], expected);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let doc = serde_json::json!({ "schema_version": CURRENT_VERSION + 1, "lists": [] });
+
+        assert!(migrate(doc).is_err());
+    }
+}
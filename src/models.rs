@@ -1,6 +1,19 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+#[cfg(feature = "gui")]
+use iced::widget::text_editor;
+#[cfg(feature = "gui")]
+use iced::window;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime};
+use uuid::Uuid;
 
-#[derive(Default, Debug, Serialize, Deserialize, PartialEq, PartialOrd)]
+pub type TaskId = Uuid;
+pub type ListId = Uuid;
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Status {
     #[default]
     Pending,
@@ -8,6 +21,32 @@ pub enum Status {
     Complete,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekly(Vec<Weekday>),
+    Monthly(u32),
+    EveryNDays(u32),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Default)]
+pub enum SortOrder {
+    #[default]
+    Insertion,
+    Priority,
+    Status,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Copy, PartialEq, Default)]
 pub enum Themes {
     #[default]
@@ -32,61 +71,383 @@ pub enum Themes {
     TokyoNightStorm,
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+/// An sRGB color, red first.
+pub type Rgb = [u8; 3];
+
+/// The colors a theme draws with, for frontends that do not use iced's
+/// themes directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Rgb,
+    pub text: Rgb,
+    pub primary: Rgb,
+    pub success: Rgb,
+    pub danger: Rgb,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StatusChange {
+    pub from: Status,
+    pub to: Status,
+    pub at: DateTime<Local>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Task {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: TaskId,
     pub title: String,
     pub status: Status,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub due_time: Option<NaiveTime>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub subtasks: Vec<Task>,
+    #[serde(default)]
+    pub collapsed: bool,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub history: Vec<StatusChange>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Written by hand so that every new task gets its own id, like a task
+/// read from a file without one.
+impl Default for Task {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title: String::new(),
+            status: Status::default(),
+            due_date: None,
+            due_time: None,
+            priority: Priority::default(),
+            tags: Vec::new(),
+            notes: None,
+            subtasks: Vec::new(),
+            collapsed: false,
+            recurrence: None,
+            created_at: None,
+            started_at: None,
+            completed_at: None,
+            history: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Tasks {
+    #[serde(default = "Uuid::new_v4")]
+    pub id: ListId,
     #[serde(default)]
     pub title: String,
     pub list: Vec<Task>,
-    #[serde(skip, default)]
+    #[serde(default)]
+    pub sort_order: SortOrder,
+}
+
+/// What is being typed and which panes are open in the list shown in the
+/// window. None of it is saved.
+#[derive(Default)]
+pub struct ListEditor {
     pub adding_after: Option<usize>,
-    #[serde(skip, default)]
     pub new_title: String,
-    #[serde(skip, default)]
-    pub editing: Option<usize>,
+    pub editing: Option<TaskId>,
+    pub new_due_date: String,
+    pub new_due_time: String,
+    pub new_tags: String,
+    pub tag_filter: Vec<String>,
+    pub notes_open: Option<TaskId>,
+    #[cfg(feature = "gui")]
+    pub notes_editor: text_editor::Content,
+    pub adding_subtask: Option<TaskId>,
+    pub new_recurrence: String,
+    pub history_open: Option<TaskId>,
+    /// Why the last add or edit was rejected, shown under its input.
+    pub error: Option<String>,
 }
 
+/// Something that was deleted, with enough context to put it back.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Trashed {
+    Task {
+        task: Box<Task>,
+        list: ListId,
+        /// Title of the list at deletion time, used if the list is gone too.
+        list_title: String,
+        /// Set for subtasks.
+        parent: Option<TaskId>,
+        position: usize,
+    },
+    List {
+        list: Tasks,
+        position: usize,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashItem {
+    pub deleted_at: DateTime<Local>,
+    pub item: Trashed,
+}
+
+/// Set when `lists.json` could not be read at startup.
+#[derive(Debug)]
+pub struct Recovery {
+    /// Where the unreadable file was moved, if moving it succeeded.
+    pub quarantined: Option<PathBuf>,
+    /// Lists read from the newest readable backup.
+    pub backup: Option<Vec<Tasks>>,
+}
+
+/// One edit to the document, in a form that can be applied again. The
+/// operations on `List` record the change that would undo each of them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Change {
+    InsertList { position: usize, list: Tasks },
+    RemoveList { id: ListId },
+    RenameList { id: ListId, title: String },
+    SetSortOrder { list: ListId, order: SortOrder },
+    InsertTask { list: ListId, position: usize, task: Box<Task> },
+    RemoveTask { list: ListId, id: TaskId },
+    RenameTask { list: ListId, id: TaskId, title: String },
+    SetPriority { list: ListId, id: TaskId, priority: Priority },
+    /// Replaces `remove` bytes of a task's notes, starting at byte `at`,
+    /// with `insert`.
+    SpliceNotes { list: ListId, id: TaskId, at: usize, remove: usize, insert: String },
+    /// Puts back a whole task, subtasks included, for edits that touch
+    /// several of its fields at once.
+    ReplaceTask { list: ListId, task: Box<Task> },
+    SetTheme { theme: Option<Themes> },
+    InsertTrash { position: usize, item: TrashItem },
+    RemoveTrash { id: Uuid },
+    ReplaceLists { lists: Vec<Tasks> },
+    ReplaceTrash { trash: Vec<TrashItem> },
+}
+
+/// The changes that step back through recent edits, and forward again.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UndoHistory {
+    /// One entry per step, oldest first. An entry's changes are applied
+    /// last to first.
+    pub undo: VecDeque<Vec<Change>>,
+    pub redo: Vec<Vec<Change>>,
+    /// Fingerprint of the document the history was saved with, so it is
+    /// dropped if the file changed before it was read back.
+    #[serde(default)]
+    pub document: u64,
+    /// Task whose notes the latest step undoes typing in; the next edit to
+    /// the same notes joins that step instead of adding one per keystroke.
+    #[serde(skip)]
+    pub coalescing: Option<TaskId>,
+}
+
+/// Set when the data file changed on disk while there were unsaved edits.
+#[derive(Debug)]
+pub struct Conflict {
+    pub lists: Vec<Tasks>,
+    pub trash: Vec<TrashItem>,
+    pub selected_theme: Option<Themes>,
+}
+
+/// The document as it is about to be written.
+pub struct Snapshot {
+    pub backend: std::sync::Arc<dyn crate::storage::Backend>,
+    pub doc: serde_json::Value,
+    /// The only tasks that changed, if nothing else in the lists did.
+    pub tasks: Option<Vec<(ListId, TaskId)>>,
+    /// Whether the data file was unreadable and its backups may still be
+    /// restored.
+    pub recovering: bool,
+}
+
+/// A snapshot from `~/Tasks/backups` as shown on the backups screen.
+#[derive(Debug, Clone)]
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+    /// `None` when the snapshot could not be read.
+    pub counts: Option<(usize, usize)>,
+}
+
+/// The document stored in the data file.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct List {
+    #[serde(default)]
+    pub schema_version: u64,
     pub lists: Vec<Tasks>,
-    #[serde(skip, default)]
-    pub selected: Option<usize>,
-    #[serde(skip, default)]
+    pub themes: Vec<Themes>,
+    pub selected_theme: Option<Themes>,
+    /// Deleted tasks and lists, newest last.
+    #[serde(default)]
+    pub trash: Vec<TrashItem>,
+    /// Undoing changes of the operations applied since it was last taken;
+    /// only kept once `record_changes` is called.
+    #[serde(skip)]
+    pub(crate) journal: Option<Vec<Change>>,
+}
+
+/// A workspace open in the window: the document, plus what is selected,
+/// being typed and waiting to be saved.
+pub struct App {
+    pub doc: List,
+    pub selected: Option<ListId>,
+    /// Inputs of the selected list; reset when another list is opened.
+    pub list_editor: ListEditor,
     pub adding_after: Option<usize>,
-    #[serde(skip, default)]
     pub new_title: String,
-    #[serde(skip, default)]
-    pub editing: Option<usize>,
-    pub themes: Vec<Themes>,
-    pub selected_theme: Option<Themes>
+    pub editing: Option<ListId>,
+    /// Why the last list edit was rejected, shown under its input.
+    pub error: Option<String>,
+    pub trash_open: bool,
+    pub recovery: Option<Recovery>,
+    /// When the latest change not yet written to disk was made.
+    pub last_change: Option<Instant>,
+    pub saving: bool,
+    /// Bumped on every change so that an older snapshot never overwrites a newer one.
+    pub revision: u64,
+    /// Newest revision written to this workspace's file, shared with the
+    /// background saves so a slow one never overwrites a newer flush.
+    pub written: std::sync::Arc<std::sync::Mutex<u64>>,
+    /// The workspace's data file, opened once and shared with background
+    /// saves; `None` if it could not be opened.
+    pub backend: Option<std::sync::Arc<dyn crate::storage::Backend>>,
+    /// Tasks changed since the last save while nothing else in the lists
+    /// did; `None` when the next save has to write the whole document.
+    pub unsaved_tasks: Option<Vec<(ListId, TaskId)>>,
+    /// Snapshots listed on the backups screen; `Some` while it is open.
+    pub backups: Option<Vec<BackupEntry>>,
+    pub backup_preview: Option<(PathBuf, Vec<Tasks>)>,
+    /// Name of the workspace this document was loaded from.
+    pub workspace: String,
+    pub workspaces: Vec<String>,
+    /// Name being typed for a new workspace; `Some` while the input is shown.
+    pub new_workspace: Option<String>,
+    /// Why the name typed for a new workspace was rejected.
+    pub workspace_error: Option<String>,
+    /// Lists and theme as last read from or written to disk, used to tell
+    /// outside edits apart from our own saves.
+    pub on_disk: Option<serde_json::Value>,
+    pub disk_modified: Option<SystemTime>,
+    pub conflict: Option<Conflict>,
+    /// Held for as long as this window may write the workspace.
+    pub instance_lock: Option<std::fs::File>,
+    /// Set when another process holds the lock; nothing is saved.
+    pub read_only: bool,
+    pub other_instance: Option<u32>,
+    pub undo: UndoHistory,
+    /// Message offering to undo a destructive action, and when it appeared.
+    pub toast: Option<(String, Instant)>,
+    /// Where the HTTP API started from this window listens. Requests are
+    /// answered by whichever workspace the window shows.
+    pub server: Option<SocketAddr>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    Forward(usize),
+    Forward(TaskId),
     AddAfter(usize),
     UpdateNewTitle(String),
+    UpdateNewDueDate(String),
+    UpdateNewDueTime(String),
+    UpdateNewTags(String),
+    UpdateNewRecurrence(String),
     ConfirmAdd,
     CancelAdd,
-    Remove(usize),
-    ChangeTitle(usize),
+    Remove(TaskId),
+    ChangeTitle(TaskId),
     ConfirmEdit,
     CancelEdit,
+    SetPriority(TaskId, Priority),
+    /// Adds a task with this title without touching the inputs.
+    AddTask(String),
+    RenameTask(TaskId, String),
+    /// A task message aimed at a list other than the selected one.
+    InList(ListId, Box<Message>),
+    SortChanged(SortOrder),
+    ToggleTagFilter(String),
+    ClearTagFilter,
+    OpenNotes(TaskId),
+    #[cfg(feature = "gui")]
+    EditNotes(text_editor::Action),
+    /// The full text of the open task's notes after an edit.
+    NotesChanged(String),
+    CloseNotes,
+    AddSubtask(TaskId),
+    ConfirmAddSubtask,
+    ForwardSubtask(TaskId, TaskId),
+    RemoveSubtask(TaskId, TaskId),
+    ToggleCollapsed(TaskId),
+    ShowHistory(TaskId),
+    CloseHistory,
 
     ThemeChanged(Themes),
-    SelectList(usize),
+    SelectList(ListId),
     BackToLists,
     AddListAfter(usize),
+    /// Appends a list with this title without touching the inputs.
+    AddList(String),
     UpdateListTitle(String),
     ConfirmAddList,
     CancelAddList,
-    RemoveList(usize),
-    ChangeListTitle(usize),
+    RemoveList(ListId),
+    ChangeListTitle(ListId),
     ConfirmListEdit,
     CancelListEdit,
+    RestoreBackup,
+    DismissRecovery,
+
+    ShowBackups,
+    CloseBackups,
+    PreviewBackup(PathBuf),
+    RestoreSnapshot(PathBuf),
+
+    SwitchWorkspace(String),
+    AddWorkspace,
+    UpdateWorkspaceName(String),
+    ConfirmAddWorkspace,
+    CancelAddWorkspace,
+
+    CheckDisk,
+    KeepMine,
+    TakeTheirs,
+    MergeChanges,
+    RetryLock,
+
+    Undo,
+    Redo,
+    DismissToast,
+
+    StartServer,
+    /// A request to the HTTP API, answered once applied.
+    Http(crate::server::Request),
+    /// The HTTP API could not listen, with the reason.
+    ServerFailed(String),
+    /// A call from the control socket, answered once applied.
+    #[cfg(unix)]
+    Rpc(crate::rpc::Request),
+
+    ShowTrash,
+    CloseTrash,
+    RestoreFromTrash(Uuid),
+    DeleteForever(Uuid),
+    EmptyTrash,
+
+    Tick,
+    Saved(Result<(), String>),
+    #[cfg(feature = "gui")]
+    CloseRequested(window::Id),
 }
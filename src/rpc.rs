@@ -0,0 +1,215 @@
+//! Line-delimited JSON-RPC 2.0 on a Unix socket next to the data file
+//! (`lists.json.sock`), for editor plugins and shell aliases talking to a
+//! running window. Each request is one line of JSON, answered by one line.
+//! Requests are handed to the window, which applies them like its own
+//! messages; this module only moves them across the socket.
+use crate::core;
+use crate::storage;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How long a connection waits for the window to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The call was understood but could not be carried out, e.g. an unknown list.
+pub const FAILED: i64 = -32000;
+
+#[derive(Debug)]
+pub struct Error {
+    pub code: i64,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: i64, message: impl ToString) -> Self {
+        Error { code, message: message.to_string() }
+    }
+}
+
+impl From<core::Error> for Error {
+    fn from(e: core::Error) -> Self {
+        let code = match e {
+            core::Error::EmptyTitle | core::Error::BadInput(_) => INVALID_PARAMS,
+            _ => FAILED,
+        };
+        Error::new(code, e)
+    }
+}
+
+/// One call waiting for the window to answer it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub params: Value,
+    reply: mpsc::Sender<Result<Value, Error>>,
+}
+
+impl Request {
+    pub fn respond(&self, result: Result<Value, Error>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+/// A required parameter. Numbers are accepted as strings, so a list can be
+/// given as `"Work"` or `2`.
+pub fn param(params: &Value, name: &str) -> Result<String, Error> {
+    match params.get(name) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(Value::Number(n)) => Ok(n.to_string()),
+        _ => Err(Error::new(INVALID_PARAMS, format!("missing parameter {name:?}"))),
+    }
+}
+
+/// A parameter deserialized as `T`, such as a `Priority`.
+pub fn typed_param<T: serde::de::DeserializeOwned>(params: &Value, name: &str) -> Result<T, Error> {
+    let value = params.get(name).cloned().unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| Error::new(INVALID_PARAMS, format!("invalid {name:?}: {e}")))
+}
+
+/// The socket belonging to the data file at `data_path`.
+pub fn socket_path(data_path: &Path) -> PathBuf {
+    storage::sibling_path(data_path, "sock")
+}
+
+/// Stops listening and removes the socket when dropped.
+pub struct Listener {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the accept loop so it sees the flag.
+        let _ = UnixStream::connect(&self.path);
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Listens on `path` from a background thread, passing every request to
+/// `forward`, which returns false once nobody is left to answer. Fails if
+/// another running instance already listens there; a socket left behind by
+/// one that has exited is replaced.
+pub fn listen(path: &Path, forward: impl Fn(Request) -> bool + Send + Sync + 'static) -> io::Result<Listener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "another instance is listening"));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let forward = Arc::new(forward);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let forward = forward.clone();
+            thread::spawn(move || serve_connection(stream, &*forward));
+        }
+    });
+    Ok(Listener { path: path.to_path_buf(), stop })
+}
+
+fn serve_connection(stream: UnixStream, forward: &dyn Fn(Request) -> bool) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(call) => call_one(&call, forward),
+            Err(e) => Some(response(Value::Null, Err(Error::new(PARSE_ERROR, e)))),
+        };
+        // Notifications, which have no id, get no response.
+        if let Some(response) = response {
+            writeln!(writer, "{response}")?;
+        }
+    }
+    Ok(())
+}
+
+fn call_one(call: &Value, forward: &dyn Fn(Request) -> bool) -> Option<Value> {
+    let id = call.get("id").cloned();
+    let result = match call.get("method").and_then(Value::as_str) {
+        None => Err(Error::new(INVALID_REQUEST, "missing \"method\"")),
+        Some(method) => {
+            let (reply, answer) = mpsc::channel();
+            let params = call.get("params").cloned().unwrap_or(Value::Null);
+            if forward(Request { method: method.to_string(), params, reply }) {
+                answer.recv_timeout(REPLY_TIMEOUT)
+                    .unwrap_or_else(|_| Err(Error::new(FAILED, "TaskMaster did not answer in time")))
+            } else {
+                Err(Error::new(FAILED, "TaskMaster is shutting down"))
+            }
+        }
+    };
+    id.map(|id| response(id, result))
+}
+
+fn response(id: Value, result: Result<Value, Error>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_forwarded_and_answered_line_by_line() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-rpc-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.json.sock");
+        let listener = listen(&path, |request| {
+            let result = match request.method.as_str() {
+                "echo" => Ok(request.params.clone()),
+                _ => Err(Error::new(METHOD_NOT_FOUND, "no such method")),
+            };
+            request.respond(result);
+            true
+        })
+        .unwrap();
+        assert!(listen(&path, |_| true).is_err(), "a second instance must not take over the socket");
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","id":1,"method":"echo","params":{{"title":"Milk"}}}}"#).unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","method":"echo"}}"#).unwrap();
+        writeln!(stream, r#"{{"jsonrpc":"2.0","id":2,"method":"nope"}}"#).unwrap();
+        writeln!(stream, "not json").unwrap();
+        let mut lines = BufReader::new(stream).lines().map(|l| serde_json::from_str::<Value>(&l.unwrap()).unwrap());
+
+        assert_eq!(lines.next().unwrap()["result"]["title"], "Milk");
+        let missing = lines.next().unwrap();
+        assert_eq!((missing["id"].clone(), missing["error"]["code"].clone()), (json!(2), json!(METHOD_NOT_FOUND)));
+        assert_eq!(lines.next().unwrap()["error"]["code"], PARSE_ERROR);
+
+        drop(listener);
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,603 @@
+//! A small HTTP/JSON API on localhost for dashboards, scripts and editor
+//! plugins. Started from a window, requests are handed to the window, which
+//! applies them to its document like its own edits. Run headless with
+//! `--serve`, every request reads the data file, applies one `core`
+//! operation and writes it back; writes are refused while a window holds
+//! the workspace's lock.
+//!
+//! Requests must carry `Host: localhost:<port>` (or `127.0.0.1`), an
+//! `Origin`, if any, must be local, and writes must be sent as
+//! `Content-Type: application/json`.
+//!
+//! Routes (ids are the full UUIDs returned by the API):
+//!
+//! - `GET /lists`, `POST /lists` with `{"title"}`
+//! - `GET|PATCH|DELETE /lists/<list>`; `PATCH` takes `{"title"}`
+//! - `GET /lists/<list>/tasks`, `POST /lists/<list>/tasks` with `{"title", "priority"?}`
+//! - `GET|PATCH|DELETE /lists/<list>/tasks/<task>`; `PATCH` takes any of
+//!   `{"title", "status", "priority"}`, where `status` may only be the one
+//!   the status button would move the task to. Nothing changes unless every
+//!   field is valid.
+//! - `POST /lists/<list>/tasks/<task>/advance` moves the task to its next
+//!   status the way the window's status button does
+use crate::cli::list_summary;
+use crate::config;
+use crate::core::Error;
+use crate::models::*;
+use serde_json::{json, Value};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+use uuid::Uuid;
+
+pub const DEFAULT_PORT: u16 = 7878;
+/// Requests larger than this are refused rather than read into memory.
+const MAX_BODY: usize = 1 << 20;
+/// How long a client may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a request waits for the window to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A status code and the JSON body to send with it.
+#[derive(Debug)]
+pub struct Reply {
+    status: u16,
+    body: Value,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Self {
+        Reply { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Reply { status, body: json!({ "error": message.to_string() }) }
+    }
+}
+
+impl From<Error> for Reply {
+    fn from(e: Error) -> Self {
+        let status = match e {
+            Error::ListNotFound(_) | Error::TaskNotFound(_) | Error::NotInTrash(_) => 404,
+            Error::EmptyTitle | Error::BadInput(_) => 400,
+            Error::Locked(_) => 409,
+            Error::Storage(_) | Error::Invalid(_) => 500,
+        };
+        Reply::error(status, e)
+    }
+}
+
+/// One request waiting for the window to answer it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    method: String,
+    target: String,
+    body: Vec<u8>,
+    reply: mpsc::Sender<Reply>,
+}
+
+impl Request {
+    /// Applies the request to `doc` and sends the reply, returning whether
+    /// `doc` changed. Writes are refused unless `writable`.
+    pub fn answer(&self, doc: &mut List, writable: bool) -> bool {
+        let write = is_write(&self.method);
+        let reply = if write && !writable {
+            Reply::error(409, "this window is read-only")
+        } else {
+            apply(doc, &self.method, &self.target, &self.body)
+        };
+        let changed = write && reply.status < 300;
+        let _ = self.reply.send(reply);
+        changed
+    }
+}
+
+/// Stops the server started by [`spawn`] when dropped.
+pub struct Listener {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl Listener {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// Binds to `port` on localhost. Port 0 picks a free one.
+pub fn bind(port: u16) -> io::Result<TcpListener> {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+}
+
+/// Serves the active workspace's data file on `port` until the process
+/// exits.
+pub fn run(port: u16) -> io::Result<()> {
+    let listener = bind(port)?;
+    eprintln!("Serving http://{} (Ctrl+C to stop)", listener.local_addr()?);
+    serve(listener, data_path);
+    Ok(())
+}
+
+/// Listens on `port` from a background thread, passing every request to
+/// `forward`, which returns false once nobody is left to answer.
+pub fn spawn(port: u16, forward: impl Fn(Request) -> bool + Send + 'static) -> io::Result<Listener> {
+    let listener = bind(port)?;
+    let addr = listener.local_addr()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    thread::spawn(move || {
+        accept(listener, &stopped, |method, target, body| {
+            let (reply, answer) = mpsc::channel();
+            let request = Request { method: method.to_string(), target: target.to_string(), body: body.to_vec(), reply };
+            if forward(request) {
+                answer.recv_timeout(REPLY_TIMEOUT)
+                    .unwrap_or_else(|_| Reply::error(503, "TaskMaster did not answer in time"))
+            } else {
+                Reply::error(503, "TaskMaster is shutting down")
+            }
+        })
+    });
+    Ok(Listener { addr, stop })
+}
+
+fn data_path() -> Option<PathBuf> {
+    crate::core::data_path(&config::active_workspace())
+}
+
+/// Answers requests against the data file at `path()`.
+pub fn serve(listener: TcpListener, path: impl Fn() -> Option<PathBuf>) {
+    accept(listener, &AtomicBool::new(false), |method, target, body| match path() {
+        Some(path) => handle(&path, method, target, body),
+        None => Reply::error(500, "could not resolve the data directory"),
+    });
+}
+
+/// Answers requests one at a time, so two clients never edit at once.
+fn accept(listener: TcpListener, stop: &AtomicBool, answer: impl Fn(&str, &str, &[u8]) -> Reply) {
+    let port = listener.local_addr().map_or(0, |addr| addr.port());
+    for stream in listener.incoming() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        let reply = match read_request(&mut stream) {
+            Ok(request) => match check_headers(&request, port) {
+                Ok(()) => answer(&request.method, &request.target, &request.body),
+                Err(reply) => reply,
+            },
+            Err(e) => Reply::error(400, e),
+        };
+        let _ = write_reply(&mut stream, reply);
+    }
+}
+
+/// Refuses requests that a web page could have sent on the user's behalf:
+/// the Host must name this server, so DNS rebinding gets nowhere, an Origin
+/// must be local, and writes must be JSON, which a page cannot send to
+/// another origin without the browser asking first.
+fn check_headers(request: &HttpRequest, port: u16) -> Result<(), Reply> {
+    let host = request.header("host").unwrap_or_default();
+    if ![format!("localhost:{port}"), format!("127.0.0.1:{port}")].iter().any(|h| h.eq_ignore_ascii_case(host)) {
+        return Err(Reply::error(403, format!("unexpected Host {host:?}")));
+    }
+    if let Some(origin) = request.header("origin") {
+        let local = origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"))
+            .map(|authority| authority.rsplit_once(':').map_or(authority, |(host, _)| host))
+            .is_some_and(|host| host.eq_ignore_ascii_case("localhost") || host == "127.0.0.1");
+        if !local {
+            return Err(Reply::error(403, format!("requests from {origin} are not allowed")));
+        }
+    }
+    let json = request.header("content-type")
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+    if is_write(&request.method) && !json {
+        return Err(Reply::error(415, "expected Content-Type: application/json"));
+    }
+    Ok(())
+}
+
+fn is_write(method: &str) -> bool {
+    method != "GET"
+}
+
+/// The parts of an HTTP/1.1 request the API looks at.
+struct HttpRequest {
+    method: String,
+    target: String,
+    /// Names in lowercase.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads one HTTP/1.1 request.
+fn read_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line"));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+
+    let mut length = 0;
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let (name, value) = (name.trim().to_ascii_lowercase(), value.trim().to_string());
+            if name == "content-length" {
+                length = value.parse().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad Content-Length"))?;
+            }
+            headers.push((name, value));
+        }
+    }
+    if length > MAX_BODY {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(HttpRequest { method, target, headers, body })
+}
+
+fn write_reply(stream: &mut TcpStream, reply: Reply) -> io::Result<()> {
+    let reason = match reply.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        403 => "Forbidden",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = match reply.status {
+        204 => String::new(),
+        _ => reply.body.to_string(),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reply.status,
+        body.len(),
+    )?;
+    stream.flush()
+}
+
+/// Applies one request to the data file at `path`, taking the workspace's
+/// lock for writes so they never race a window's saves.
+fn handle(path: &Path, method: &str, target: &str, body: &[u8]) -> Reply {
+    let write = is_write(method);
+    let _lock = match write.then(|| crate::core::lock(path)).transpose() {
+        Ok(lock) => lock,
+        Err(Error::Locked(_)) => {
+            return Reply::error(409, "the workspace is open in TaskMaster; start the API from its window instead");
+        }
+        Err(e) => return e.into(),
+    };
+    let opened = if write { List::open(path) } else { List::read(path) };
+    let mut app = match opened {
+        Ok(app) => app,
+        Err(e) => return e.into(),
+    };
+    let reply = apply(&mut app, method, target, body);
+    if write
+        && reply.status < 300
+        && let Err(e) = app.save_to(path) {
+        return e.into();
+    }
+    reply
+}
+
+/// Applies one request to `app`.
+fn apply(app: &mut List, method: &str, target: &str, body: &[u8]) -> Reply {
+    let segments: Vec<&str> = target
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let body = match body {
+        [] => Value::Null,
+        body => match serde_json::from_slice(body) {
+            Ok(body) => body,
+            Err(e) => return Reply::error(400, format!("invalid JSON: {e}")),
+        },
+    };
+    match route(app, method, &segments, &body) {
+        Ok(reply) | Err(reply) => reply,
+    }
+}
+
+fn route(app: &mut List, method: &str, segments: &[&str], body: &Value) -> Result<Reply, Reply> {
+    match (method, segments) {
+        ("GET", ["lists"]) => Ok(Reply::ok(json!(app.lists.iter().map(list_summary).collect::<Vec<_>>()))),
+        ("POST", ["lists"]) => {
+            let id = app.add_list(field(body, "title")?)?;
+            Ok(Reply { status: 201, body: list_summary(find_list(app, id)?) })
+        }
+        ("GET", ["lists", list]) => {
+            let list = find_list(app, parse_id(list)?)?;
+            Ok(Reply::ok(json!({ "id": list.id, "title": list.title, "tasks": list.list })))
+        }
+        ("PATCH", ["lists", list]) => {
+            let list = parse_id(list)?;
+            app.rename_list(list, field(body, "title")?)?;
+            Ok(Reply::ok(list_summary(find_list(app, list)?)))
+        }
+        ("DELETE", ["lists", list]) => {
+            app.remove_list(parse_id(list)?)?;
+            Ok(Reply { status: 204, body: Value::Null })
+        }
+        ("GET", ["lists", list, "tasks"]) => Ok(Reply::ok(json!(find_list(app, parse_id(list)?)?.list))),
+        ("POST", ["lists", list, "tasks"]) => {
+            let list = parse_id(list)?;
+            let priority = optional(body, "priority")?;
+            let task = app.add_task(list, field(body, "title")?)?;
+            if let Some(priority) = priority {
+                app.set_priority(list, task, priority)?;
+            }
+            Ok(Reply { status: 201, body: task_json(app, list, task)? })
+        }
+        ("GET", ["lists", list, "tasks", task]) => Ok(Reply::ok(task_json(app, parse_id(list)?, parse_id(task)?)?)),
+        ("PATCH", ["lists", list, "tasks", task]) => {
+            let (list, task) = (parse_id(list)?, parse_id(task)?);
+            // Check every field first so a bad one leaves the task untouched.
+            let title = body.get("title").map(|_| field(body, "title")).transpose()?;
+            if title.is_some_and(|title| title.trim().is_empty()) {
+                return Err(Error::EmptyTitle.into());
+            }
+            let priority = optional(body, "priority")?;
+            let current = find_task(app, list, task)?.status;
+            let advance = match optional::<Status>(body, "status")? {
+                Some(status) if status == current => false,
+                Some(status) if status == current.next() => true,
+                Some(status) => {
+                    return Err(Reply::error(400, format!(
+                        "a {current:?} task can only move to {:?}, not {status:?}",
+                        current.next(),
+                    )));
+                }
+                None => false,
+            };
+            if let Some(title) = title {
+                app.rename_task(list, task, title)?;
+            }
+            if let Some(priority) = priority {
+                app.set_priority(list, task, priority)?;
+            }
+            if advance {
+                app.advance_task(list, task)?;
+            }
+            Ok(Reply::ok(task_json(app, list, task)?))
+        }
+        ("DELETE", ["lists", list, "tasks", task]) => {
+            app.remove_task(parse_id(list)?, parse_id(task)?)?;
+            Ok(Reply { status: 204, body: Value::Null })
+        }
+        ("POST", ["lists", list, "tasks", task, "advance"]) => {
+            let (list, task) = (parse_id(list)?, parse_id(task)?);
+            app.advance_task(list, task)?;
+            Ok(Reply::ok(task_json(app, list, task)?))
+        }
+        (_, ["lists"] | ["lists", _] | ["lists", _, "tasks"] | ["lists", _, "tasks", _] | ["lists", _, "tasks", _, "advance"]) => {
+            Err(Reply::error(405, format!("{method} is not supported here")))
+        }
+        _ => Err(Reply::error(404, "no such endpoint")),
+    }
+}
+
+fn parse_id(segment: &str) -> Result<Uuid, Reply> {
+    segment.parse().map_err(|_| Reply::error(404, format!("{segment:?} is not an id")))
+}
+
+fn find_list(app: &List, id: ListId) -> Result<&Tasks, Reply> {
+    app.list(id).ok_or_else(|| Error::ListNotFound(id).into())
+}
+
+fn find_task(app: &List, list: ListId, id: TaskId) -> Result<&Task, Reply> {
+    find_list(app, list)?.task(id).ok_or_else(|| Error::TaskNotFound(id).into())
+}
+
+fn task_json(app: &List, list: ListId, id: TaskId) -> Result<Value, Reply> {
+    Ok(json!(find_task(app, list, id)?))
+}
+
+/// A required string field of the request body.
+fn field<'a>(body: &'a Value, name: &str) -> Result<&'a str, Reply> {
+    body.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| Reply::error(400, format!("expected a string {name:?} in the request body")))
+}
+
+/// An optional field of the request body, such as a `Status` or `Priority`.
+fn optional<T: serde::de::DeserializeOwned>(body: &Value, name: &str) -> Result<Option<T>, Reply> {
+    match body.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| Reply::error(400, format!("invalid {name:?}: {e}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(addr: SocketAddr, method: &str, target: &str, body: Value) -> (u16, Value) {
+        let host = format!("localhost:{}", addr.port());
+        send(addr, method, target, &[("Host", &host), ("Content-Type", "application/json")], body)
+    }
+
+    fn send(addr: SocketAddr, method: &str, target: &str, headers: &[(&str, &str)], body: Value) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let body = if body.is_null() { String::new() } else { body.to_string() };
+        write!(stream, "{method} {target} HTTP/1.1\r\n").unwrap();
+        for (name, value) in headers {
+            write!(stream, "{name}: {value}\r\n").unwrap();
+        }
+        write!(stream, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+
+    #[test]
+    fn tasks_can_be_created_advanced_and_deleted_over_http() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-server-{}", Uuid::new_v4()));
+        let path = dir.join("lists.json");
+        let listener = bind(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = path.clone();
+        thread::spawn(move || serve(listener, move || Some(served.clone())));
+
+        let (status, list) = request(addr, "POST", "/lists", json!({ "title": "Work" }));
+        assert_eq!(status, 201);
+        let tasks = format!("/lists/{}/tasks", list["id"].as_str().unwrap());
+        let (status, task) = request(addr, "POST", &tasks, json!({ "title": "Report", "priority": "High" }));
+        assert_eq!(status, 201);
+        assert_eq!(task["priority"], "High");
+        let task_url = format!("{tasks}/{}", task["id"].as_str().unwrap());
+
+        let (_, task) = request(addr, "POST", &format!("{task_url}/advance"), Value::Null);
+        assert_eq!(task["status"], "InProgress");
+        let (_, task) = request(addr, "PATCH", &task_url, json!({ "status": "Complete" }));
+        assert_eq!(task["status"], "Complete");
+        assert_eq!(request(addr, "POST", &tasks, json!({ "title": " " })).0, 400);
+        assert_eq!(request(addr, "PUT", &task_url, Value::Null).0, 405);
+
+        assert_eq!(request(addr, "DELETE", &task_url, Value::Null).0, 204);
+        assert_eq!(request(addr, "GET", &task_url, Value::Null).0, 404);
+        let app = List::open(&path).unwrap();
+        assert_eq!(app.trash.len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_bad_field_leaves_the_task_unchanged() {
+        let mut app = List::new();
+        let list = app.add_list("Work").unwrap();
+        let id = app.add_task(list, "Report").unwrap();
+        let tasks = format!("/lists/{list}/tasks");
+        let task = format!("{tasks}/{id}");
+        let patch = |app: &mut List, body: Value| apply(app, "PATCH", &task, body.to_string().as_bytes()).status;
+
+        assert_eq!(apply(&mut app, "POST", &tasks, json!({ "title": "Slides", "priority": "Huge" }).to_string().as_bytes()).status, 400);
+        assert_eq!(patch(&mut app, json!({ "title": "Final report", "priority": "Huge" })), 400);
+        assert_eq!(patch(&mut app, json!({ "title": "Final report", "status": "Complete" })), 400);
+        assert_eq!(patch(&mut app, json!({ "title": " ", "priority": "High" })), 400);
+        let tasks = &app.list(list).unwrap().list;
+        assert_eq!(tasks.len(), 1);
+        assert_eq!((tasks[0].title.as_str(), tasks[0].priority, tasks[0].status), ("Report", Priority::None, Status::Pending));
+
+        assert_eq!(patch(&mut app, json!({ "status": "InProgress" })), 200);
+        assert_eq!(patch(&mut app, json!({ "status": "Complete" })), 200);
+        assert_eq!(patch(&mut app, json!({ "status": "Pending" })), 400);
+        assert_eq!(app.list(list).unwrap().list[0].status, Status::Complete);
+    }
+
+    #[test]
+    fn requests_a_web_page_could_forge_are_refused() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-server-{}", Uuid::new_v4()));
+        let listener = bind(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = dir.join("lists.json");
+        thread::spawn(move || serve(listener, move || Some(served.clone())));
+        let host = format!("127.0.0.1:{}", addr.port());
+        let json = ("Content-Type", "application/json");
+        let title = json!({ "title": "Work" });
+
+        assert_eq!(send(addr, "GET", "/lists", &[("Host", "evil.example")], Value::Null).0, 403);
+        assert_eq!(send(addr, "GET", "/lists", &[], Value::Null).0, 403);
+        let from_page = [("Host", host.as_str()), ("Origin", "https://evil.example"), json];
+        assert_eq!(send(addr, "POST", "/lists", &from_page, title.clone()).0, 403);
+        let as_form = [("Host", host.as_str()), ("Content-Type", "text/plain")];
+        assert_eq!(send(addr, "POST", "/lists", &as_form, title.clone()).0, 415);
+        let local = [("Host", host.as_str()), ("Origin", "http://localhost:3000"), ("Content-Type", "application/json; charset=utf-8")];
+        assert_eq!(send(addr, "POST", "/lists", &local, title).0, 201);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn serving_the_file_refuses_writes_while_a_window_has_it_open() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-server-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.json");
+        let window = crate::storage::lock(&path).unwrap();
+        let listener = bind(0).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = path.clone();
+        thread::spawn(move || serve(listener, move || Some(served.clone())));
+
+        assert_eq!(request(addr, "POST", "/lists", json!({ "title": "Work" })).0, 409);
+        assert_eq!(request(addr, "GET", "/lists", Value::Null).0, 200);
+        drop(window);
+        assert_eq!(request(addr, "POST", "/lists", json!({ "title": "Work" })).0, 201);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn a_window_answers_requests_from_its_own_document() {
+        let (sender, requests) = mpsc::channel();
+        let listener = spawn(0, move |request| sender.send(request).is_ok()).unwrap();
+        let addr = listener.addr();
+        let window = thread::spawn(move || {
+            let mut doc = List::new();
+            let request: Request = requests.recv().unwrap();
+            let changed = request.answer(&mut doc, true);
+            let request: Request = requests.recv().unwrap();
+            (doc, changed, request.answer(&mut List::new(), false))
+        });
+
+        assert_eq!(request(addr, "POST", "/lists", json!({ "title": "Work" })).0, 201);
+        assert_eq!(request(addr, "POST", "/lists", json!({ "title": "Home" })).0, 409);
+        let (doc, changed, read_only_changed) = window.join().unwrap();
+        assert_eq!(doc.lists[0].title, "Work");
+        assert!(changed && !read_only_changed);
+
+        drop(listener);
+        let freed = (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            bind(addr.port()).is_ok()
+        });
+        assert!(freed, "the port is freed once the listener is dropped");
+    }
+}
@@ -0,0 +1,53 @@
+use super::{write_atomic, write_atomic_keeping_backup, Backend, StorageError};
+use serde_json::Value;
+use std::{
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The default backend: one pretty-printed JSON file, replaced atomically on
+/// every save.
+pub struct JsonFile {
+    path: PathBuf,
+}
+
+impl JsonFile {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf() }
+    }
+}
+
+impl Backend for JsonFile {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Option<Value>, StorageError> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Err(StorageError::Corrupt(e.to_string()));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| StorageError::Corrupt(e.to_string()))
+    }
+
+    fn save(&self, doc: &Value) -> Result<(), StorageError> {
+        write_atomic(&self.path, to_json(doc)?.as_bytes())?;
+        Ok(())
+    }
+
+    fn save_keeping_backup(&self, doc: &Value) -> Result<(), StorageError> {
+        write_atomic_keeping_backup(&self.path, to_json(doc)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn to_json(doc: &Value) -> Result<String, StorageError> {
+    serde_json::to_string_pretty(doc).map_err(|e| StorageError::Corrupt(e.to_string()))
+}
@@ -0,0 +1,556 @@
+use crate::migrations;
+use crate::models::{ListId, TaskId};
+use chrono::{Duration, Local, NaiveDateTime, Timelike};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+mod json;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use json::JsonFile;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteFile;
+
+const SNAPSHOT_PREFIX: &str = "lists-";
+const SNAPSHOT_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// Snapshots younger than this are kept one per hour...
+const HOURLY_RETENTION: Duration = Duration::hours(24);
+/// ...and older ones one per day, until they reach this age.
+const DAILY_RETENTION: Duration = Duration::days(30);
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    /// The data exists but could not be understood.
+    Corrupt(String),
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(e) => write!(f, "{e}"),
+            StorageError::Corrupt(e) => write!(f, "unreadable data: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
+
+/// Where and how the document is persisted. Documents are exchanged as
+/// `serde_json::Value`s in the on-disk schema, so backends never see UI
+/// state and migrations apply to every backend alike.
+pub trait Backend: Send + Sync {
+    /// The file holding the data, used for backups and recovery.
+    fn path(&self) -> &Path;
+
+    /// Reads the whole document, or `None` if nothing has been stored yet.
+    fn load(&self) -> Result<Option<Value>, StorageError>;
+
+    /// Replaces the whole document.
+    fn save(&self, doc: &Value) -> Result<(), StorageError>;
+
+    /// Inserts or replaces a single task in the list with id `list`.
+    fn upsert_task(&self, list: ListId, task: &Value) -> Result<(), StorageError> {
+        let mut doc = self.load_current()?;
+        let tasks = find_by_id(&mut doc["lists"], &list.to_string())
+            .and_then(|list| list.get_mut("list"))
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| StorageError::Corrupt(format!("no list with id {list}")))?;
+        let id = task.get("id").cloned().unwrap_or_default();
+        match tasks.iter_mut().find(|t| t.get("id") == Some(&id)) {
+            Some(existing) => *existing = task.clone(),
+            None => tasks.push(task.clone()),
+        }
+        self.save(&doc)
+    }
+
+    /// Writes `doc` when, since the last save, only the tasks in `tasks` and
+    /// the fields outside the lists changed. Backends that store the
+    /// document in one piece write all of it.
+    fn save_tasks(&self, doc: &Value, tasks: &[(ListId, TaskId)]) -> Result<(), StorageError> {
+        let _ = tasks;
+        self.save(doc)
+    }
+
+    /// Like `save`, but leaves `<name>.bak` as it is. Used while the window
+    /// still offers to restore from it after the data file was unreadable.
+    fn save_keeping_backup(&self, doc: &Value) -> Result<(), StorageError> {
+        self.save(doc)
+    }
+
+    /// Removes a single task from whichever list holds it.
+    fn delete_task(&self, id: TaskId) -> Result<(), StorageError> {
+        let mut doc = self.load_current()?;
+        let id = Value::from(id.to_string());
+        for list in doc["lists"].as_array_mut().into_iter().flatten() {
+            if let Some(tasks) = list.get_mut("list").and_then(Value::as_array_mut) {
+                tasks.retain(|t| t.get("id") != Some(&id));
+            }
+        }
+        self.save(&doc)
+    }
+
+    /// Writes a consistent copy of the data to `target`, which is replaced
+    /// only once the copy is complete.
+    fn snapshot_to(&self, target: &Path) -> Result<(), StorageError> {
+        replace_with(target, |tmp| fs::copy(self.path(), tmp).map(drop).map_err(StorageError::from))
+    }
+
+    /// Like `load`, but migrated to the current schema and never empty.
+    fn load_current(&self) -> Result<Value, StorageError> {
+        let doc = self.load()?
+            .ok_or_else(|| StorageError::Corrupt(String::from("no data stored yet")))?;
+        migrations::migrate(doc).map_err(StorageError::Corrupt)
+    }
+}
+
+fn find_by_id<'a>(items: &'a mut Value, id: &str) -> Option<&'a mut Value> {
+    items.as_array_mut()?
+        .iter_mut()
+        .find(|item| item.get("id").and_then(Value::as_str) == Some(id))
+}
+
+/// Opens the backend matching the file extension of `path`.
+pub fn open(path: &Path) -> Result<Box<dyn Backend>, StorageError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "sqlite")]
+        Some("db" | "sqlite") => Ok(Box::new(SqliteFile::open(path)?)),
+        _ => Ok(Box::new(JsonFile::new(path))),
+    }
+}
+
+/// Opens a file only to read it, such as a snapshot in the backups
+/// directory, without creating or changing anything.
+pub fn open_read_only(path: &Path) -> Result<Box<dyn Backend>, StorageError> {
+    match path.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "sqlite")]
+        Some("db" | "sqlite") => Ok(Box::new(SqliteFile::open_read_only(path)?)),
+        _ => Ok(Box::new(JsonFile::new(path))),
+    }
+}
+
+/// `lists.json`, or `lists.db` when built with the `sqlite` feature and run
+/// with `TASKMASTER_STORAGE=sqlite`.
+pub fn data_file_name() -> &'static str {
+    #[cfg(feature = "sqlite")]
+    if std::env::var("TASKMASTER_STORAGE").is_ok_and(|s| s.eq_ignore_ascii_case("sqlite")) {
+        return "lists.db";
+    }
+    "lists.json"
+}
+
+/// `lists.json` -> `lists.json.<suffix>`
+pub fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, "bak")
+}
+
+/// `~/Tasks/lists.json` -> `~/Tasks/backups`
+pub fn backups_dir(path: &Path) -> PathBuf {
+    path.with_file_name("backups")
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+}
+
+/// Every snapshot in `dir`, newest first.
+pub fn snapshots(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let (stamp, _extension) = name.strip_prefix(SNAPSHOT_PREFIX)?.rsplit_once('.')?;
+            let taken_at = NaiveDateTime::parse_from_str(stamp, SNAPSHOT_FORMAT).ok()?;
+            Some(Snapshot { path: entry.path(), taken_at })
+        })
+        .collect();
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.taken_at));
+    snapshots
+}
+
+/// Copies the data into the backups directory if the newest snapshot is more
+/// than an hour old, then prunes snapshots that fall outside the retention
+/// policy.
+pub fn rotate_snapshots(backend: &dyn Backend) -> Result<(), StorageError> {
+    let path = backend.path();
+    let dir = backups_dir(path);
+    let now = Local::now().naive_local();
+    let existing = snapshots(&dir);
+    if existing.first().is_none_or(|newest| now - newest.taken_at >= Duration::hours(1)) {
+        fs::create_dir_all(&dir)?;
+        let extension = path.extension().unwrap_or_default().to_string_lossy();
+        let name = format!("{SNAPSHOT_PREFIX}{}.{extension}", now.format(SNAPSHOT_FORMAT));
+        backend.snapshot_to(&dir.join(name))?;
+    }
+
+    let snapshots = snapshots(&dir);
+    let taken: Vec<NaiveDateTime> = snapshots.iter().map(|s| s.taken_at).collect();
+    let expired = expired_snapshots(&taken, now);
+    for snapshot in snapshots.iter().filter(|s| expired.contains(&s.taken_at)) {
+        fs::remove_file(&snapshot.path)?;
+    }
+    Ok(())
+}
+
+/// Applies the retention policy: the newest snapshot of each hour for the
+/// last day, the newest of each day for the last month, nothing older.
+/// `taken` must be sorted newest first.
+fn expired_snapshots(taken: &[NaiveDateTime], now: NaiveDateTime) -> Vec<NaiveDateTime> {
+    let mut hours = HashSet::new();
+    let mut days = HashSet::new();
+    taken
+        .iter()
+        .copied()
+        .filter(|&at| {
+            let age = now - at;
+            let keep = if age < HOURLY_RETENTION {
+                hours.insert(at.date().and_hms_opt(at.hour(), 0, 0))
+            } else if age < DAILY_RETENTION {
+                days.insert(at.date())
+            } else {
+                false
+            };
+            !keep
+        })
+        .collect()
+}
+
+/// SQLite's write-ahead log and shared-memory files, which belong to the
+/// database at `path` and have to move along with it.
+fn sqlite_companions(path: &Path) -> [PathBuf; 2] {
+    ["-wal", "-shm"].map(|suffix| {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    })
+}
+
+/// Moves an unreadable `lists.json` aside as `lists.corrupt-<timestamp>.json`
+/// so that the next save cannot overwrite it, adding a counter if a file
+/// was already quarantined within the same second. A database moves
+/// together with its `-wal` and `-shm` files.
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let extension = path.extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let target = (1..)
+        .map(|n| match n {
+            1 => format!("{stem}.corrupt-{timestamp}{extension}"),
+            n => format!("{stem}.corrupt-{timestamp}-{n}{extension}"),
+        })
+        .map(|name| path.with_file_name(name))
+        .find(|target| !target.exists() && sqlite_companions(target).iter().all(|c| !c.exists()))
+        .expect("some counter is unused");
+    fs::rename(path, &target)?;
+    for (from, to) in sqlite_companions(path).into_iter().zip(sqlite_companions(&target)) {
+        if from.exists() {
+            fs::rename(from, to)?;
+        }
+    }
+    Ok(target)
+}
+
+/// Why [`lock`] did not return a lock.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process holds the lock; carries its pid if it could be read.
+    Held(Option<u32>),
+    Io(io::Error),
+}
+
+/// Takes the advisory lock `<name>.lock` next to the data file, so that only
+/// one process writes it at a time. The lock is released when the returned
+/// file is dropped or the process exits.
+pub fn lock(path: &Path) -> Result<File, LockError> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(sibling_path(path, "lock"))
+        .map_err(LockError::Io)?;
+    match file.try_lock() {
+        Ok(()) => {
+            let pid = std::process::id().to_string();
+            file.set_len(0)
+                .and_then(|_| file.rewind())
+                .and_then(|_| file.write_all(pid.as_bytes()))
+                .map_err(LockError::Io)?;
+            Ok(file)
+        }
+        Err(fs::TryLockError::WouldBlock) => {
+            let mut pid = String::new();
+            let _ = file.read_to_string(&mut pid);
+            Err(LockError::Held(pid.trim().parse().ok()))
+        }
+        Err(fs::TryLockError::Error(e)) => Err(LockError::Io(e)),
+    }
+}
+
+/// Replaces `path` with `contents` so that a crash at any point leaves either
+/// the old or the new file in place, never a truncated one. The previous
+/// version is kept next to it as `<name>.bak`.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    write_atomic_with(path, true, |file| file.write_all(contents))
+}
+
+/// Like [`write_atomic`], but leaves `<name>.bak` untouched.
+pub fn write_atomic_keeping_backup(path: &Path, contents: &[u8]) -> io::Result<()> {
+    write_atomic_with(path, false, |file| file.write_all(contents))
+}
+
+fn write_atomic_with<F>(path: &Path, backup: bool, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let tmp = sibling_path(path, "tmp");
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        write(&mut file)?;
+        file.sync_all()
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    if backup && path.exists() {
+        replace_with(&backup_path(path), |bak| {
+            fs::hard_link(path, bak).or_else(|_| fs::copy(path, bak).map(drop))
+        })?;
+    }
+    fs::rename(&tmp, path)?;
+    sync_parent_dir(path);
+    Ok(())
+}
+
+/// Creates `target` by writing it under a temporary name and renaming it into
+/// place, so that a crash never leaves a partial `target` behind.
+fn replace_with<F, E>(target: &Path, create: F) -> Result<(), E>
+where
+    F: FnOnce(&Path) -> Result<(), E>,
+    E: From<io::Error>,
+{
+    let tmp = sibling_path(target, "tmp");
+    let _ = fs::remove_file(&tmp);
+    if let Err(e) = create(&tmp) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, target)?;
+    Ok(())
+}
+
+/// Makes the rename itself durable. Not supported on every platform, so
+/// failures are ignored.
+fn sync_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn second_lock_reports_the_holder() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+
+        let held = lock(&path).unwrap();
+        match lock(&path) {
+            Err(LockError::Held(pid)) => assert_eq!(pid, Some(std::process::id())),
+            other => panic!("expected the lock to be held, got {other:?}"),
+        }
+        drop(held);
+        assert!(lock(&path).is_ok());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_new_file_without_backup() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+
+        write_atomic(&path, b"first").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+        assert!(!backup_path(&path).exists());
+        assert!(!sibling_path(&path, "tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_previous_version_as_backup() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"first");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup_is_the_previous_file_itself() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        write_atomic(&path, b"third").unwrap();
+
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"second");
+        assert!(!sibling_path(&backup_path(&path), "tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn partial_write_leaves_original_untouched() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+        write_atomic(&path, b"{\"lists\":[]}").unwrap();
+
+        let result = write_atomic_with(&path, true, |file| {
+            file.write_all(b"{\"lis")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"{\"lists\":[]}");
+        assert!(!backup_path(&path).exists());
+        assert!(!sibling_path(&path, "tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn quarantine_moves_corrupt_file_aside() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+        fs::write(&path, b"{not json").unwrap();
+
+        let target = quarantine(&path).unwrap();
+
+        assert!(!path.exists());
+        assert_eq!(fs::read(&target).unwrap(), b"{not json");
+        let name = target.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("lists.corrupt-") && name.ends_with(".json"), "{name}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn quarantining_twice_keeps_both_files() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+        fs::write(&path, b"first").unwrap();
+        let first = quarantine(&path).unwrap();
+        fs::write(&path, b"second").unwrap();
+        let second = quarantine(&path).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), b"first");
+        assert_eq!(fs::read(&second).unwrap(), b"second");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saving_while_recovering_leaves_the_backup_alone() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+        fs::write(backup_path(&path), b"backup").unwrap();
+
+        write_atomic_keeping_backup(&path, b"first").unwrap();
+        write_atomic_keeping_backup(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"backup");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn quarantine_takes_the_database_journal_along() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.db");
+        fs::write(&path, b"not a database").unwrap();
+        let [wal, shm] = sqlite_companions(&path);
+        fs::write(&wal, b"wal").unwrap();
+        fs::write(&shm, b"shm").unwrap();
+
+        let target = quarantine(&path).unwrap();
+
+        assert!(!wal.exists() && !shm.exists());
+        let [moved_wal, moved_shm] = sqlite_companions(&target);
+        assert_eq!(fs::read(moved_wal).unwrap(), b"wal");
+        assert_eq!(fs::read(moved_shm).unwrap(), b"shm");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn retention_keeps_hourly_then_daily_snapshots() {
+        let at = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+        let now = at("2026-03-31 12:00");
+        let taken = [
+            at("2026-03-31 11:50"),
+            at("2026-03-31 11:10"),
+            at("2026-03-31 09:30"),
+            at("2026-03-30 08:00"),
+            at("2026-03-29 18:00"),
+            at("2026-03-29 07:00"),
+            at("2026-02-01 07:00"),
+        ];
+
+        let expired = expired_snapshots(&taken, now);
+
+        assert_eq!(expired, vec![taken[1], taken[5], taken[6]]);
+    }
+
+    #[test]
+    fn stale_temp_file_from_crash_is_replaced() {
+        let dir = scratch_dir();
+        let path = dir.join("lists.json");
+        write_atomic(&path, b"good").unwrap();
+        fs::write(sibling_path(&path, "tmp"), b"truncat").unwrap();
+
+        write_atomic(&path, b"better").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"better");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"good");
+        assert!(!sibling_path(&path, "tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,359 @@
+use super::{replace_with, Backend, StorageError};
+use crate::models::{ListId, TaskId};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Transaction};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    PRAGMA foreign_keys = ON;
+    CREATE TABLE IF NOT EXISTS meta (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS lists (
+        id       TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data     TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id       TEXT PRIMARY KEY,
+        list_id  TEXT NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        data     TEXT NOT NULL
+    );
+";
+
+/// Stores each list and task as its own row, so saving only touches rows that
+/// changed and other processes can read while the app writes.
+pub struct SqliteFile {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::SqliteFailure(ref err, _)
+                if matches!(err.code, rusqlite::ErrorCode::NotADatabase | rusqlite::ErrorCode::DatabaseCorrupt) =>
+            {
+                StorageError::Corrupt(e.to_string())
+            }
+            e => StorageError::Io(std::io::Error::other(e)),
+        }
+    }
+}
+
+fn to_text(value: &Value) -> Result<String, StorageError> {
+    serde_json::to_string(value).map_err(|e| StorageError::Corrupt(e.to_string()))
+}
+
+fn from_text(text: &str) -> Result<Value, StorageError> {
+    serde_json::from_str(text).map_err(|e| StorageError::Corrupt(e.to_string()))
+}
+
+fn id_of(item: &Map<String, Value>) -> Result<String, StorageError> {
+    item.get("id")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| StorageError::Corrupt(String::from("item without an id")))
+}
+
+/// Writes a row into `table` unless the stored row is already identical.
+fn upsert(
+    tx: &Transaction,
+    table: &str,
+    id: &str,
+    list_id: Option<&str>,
+    position: usize,
+    data: &str,
+) -> Result<(), StorageError> {
+    let position = position as i64;
+    match list_id {
+        Some(list_id) => tx.execute(
+            &format!(
+                "INSERT INTO {table} (id, list_id, position, data) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(id) DO UPDATE SET list_id = ?2, position = ?3, data = ?4
+                 WHERE list_id != ?2 OR position != ?3 OR data != ?4"
+            ),
+            params![id, list_id, position, data],
+        )?,
+        None => tx.execute(
+            &format!(
+                "INSERT INTO {table} (id, position, data) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET position = ?2, data = ?3
+                 WHERE position != ?2 OR data != ?3"
+            ),
+            params![id, position, data],
+        )?,
+    };
+    Ok(())
+}
+
+/// Stores everything but the lists, returning the lists.
+fn save_meta(tx: &Transaction, doc: &Value) -> Result<Value, StorageError> {
+    let mut meta = doc.as_object().cloned().unwrap_or_default();
+    let lists = meta.remove("lists").unwrap_or_default();
+    tx.execute(
+        "INSERT INTO meta (key, value) VALUES ('document', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1 WHERE value != ?1",
+        [to_text(&Value::Object(meta))?],
+    )?;
+    Ok(lists)
+}
+
+/// Inserts or replaces a task, keeping its position if it is already
+/// stored and appending it to `list` otherwise.
+fn upsert_task(tx: &Transaction, list: ListId, task: &Value) -> Result<(), StorageError> {
+    let Some(obj) = task.as_object() else {
+        return Err(StorageError::Corrupt(String::from("task is not an object")));
+    };
+    let task_id = id_of(obj)?;
+    let list_id = list.to_string();
+    let position: i64 = tx.query_row(
+        "SELECT COALESCE(
+             (SELECT position FROM tasks WHERE id = ?1),
+             (SELECT MAX(position) + 1 FROM tasks WHERE list_id = ?2),
+             0)",
+        params![task_id, list_id],
+        |row| row.get(0),
+    )?;
+    upsert(tx, "tasks", &task_id, Some(&list_id), position as usize, &to_text(task)?)
+}
+
+fn delete_task(tx: &Transaction, id: TaskId) -> Result<(), StorageError> {
+    tx.execute("DELETE FROM tasks WHERE id = ?1", [id.to_string()])?;
+    Ok(())
+}
+
+fn delete_missing(
+    tx: &Transaction,
+    table: &str,
+    keep: &HashSet<String>,
+) -> Result<(), StorageError> {
+    let existing: Vec<String> = tx
+        .prepare(&format!("SELECT id FROM {table}"))?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    for id in existing.iter().filter(|id| !keep.contains(*id)) {
+        tx.execute(&format!("DELETE FROM {table} WHERE id = ?1"), [id])?;
+    }
+    Ok(())
+}
+
+impl SqliteFile {
+    pub fn open(path: &Path) -> Result<Self, StorageError> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { path: path.to_path_buf(), conn: Mutex::new(conn) })
+    }
+
+    /// Opens an existing database without creating tables or files, for
+    /// reading snapshots.
+    pub fn open_read_only(path: &Path) -> Result<Self, StorageError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        Ok(Self { path: path.to_path_buf(), conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Backend for SqliteFile {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Option<Value>, StorageError> {
+        let conn = self.conn();
+        let meta: Option<String> = conn
+            .query_row("SELECT value FROM meta WHERE key = 'document'", [], |row| row.get(0))
+            .optional()?;
+        let Some(meta) = meta else {
+            return Ok(None);
+        };
+        let mut doc = from_text(&meta)?;
+
+        let mut tasks_by_list: Vec<(String, Value)> = Vec::new();
+        let mut stmt = conn.prepare("SELECT list_id, data FROM tasks ORDER BY position")?;
+        for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+            let (list_id, data) = row?;
+            tasks_by_list.push((list_id, from_text(&data)?));
+        }
+
+        let mut lists = Vec::new();
+        let mut stmt = conn.prepare("SELECT id, data FROM lists ORDER BY position")?;
+        for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+            let (id, data) = row?;
+            let mut list = from_text(&data)?;
+            let tasks = tasks_by_list.iter()
+                .filter(|(list_id, _)| *list_id == id)
+                .map(|(_, task)| task.clone())
+                .collect();
+            if let Some(obj) = list.as_object_mut() {
+                obj.insert(String::from("list"), Value::Array(tasks));
+            }
+            lists.push(list);
+        }
+
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert(String::from("lists"), Value::Array(lists));
+        }
+        Ok(Some(doc))
+    }
+
+    fn save(&self, doc: &Value) -> Result<(), StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let lists = save_meta(&tx, doc)?;
+
+        let mut list_ids = HashSet::new();
+        let mut task_ids = HashSet::new();
+        for (position, list) in lists.as_array().into_iter().flatten().enumerate() {
+            let Some(list) = list.as_object() else { continue };
+            let list_id = id_of(list)?;
+            let mut data = list.clone();
+            let tasks = data.remove("list").unwrap_or_default();
+            upsert(&tx, "lists", &list_id, None, position, &to_text(&Value::Object(data))?)?;
+
+            for (position, task) in tasks.as_array().into_iter().flatten().enumerate() {
+                let Some(obj) = task.as_object() else { continue };
+                let task_id = id_of(obj)?;
+                upsert(&tx, "tasks", &task_id, Some(&list_id), position, &to_text(task)?)?;
+                task_ids.insert(task_id);
+            }
+            list_ids.insert(list_id);
+        }
+        delete_missing(&tx, "tasks", &task_ids)?;
+        delete_missing(&tx, "lists", &list_ids)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Writes only the rows of `tasks`, then renumbers the tasks of their
+    /// lists so the stored order matches `doc`.
+    fn save_tasks(&self, doc: &Value, tasks: &[(ListId, TaskId)]) -> Result<(), StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let lists = save_meta(&tx, doc)?;
+        let list_tasks = |list: ListId| {
+            let list = list.to_string();
+            lists.as_array()
+                .and_then(|lists| lists.iter().find(|l| l.get("id").and_then(Value::as_str) == Some(&list)))
+                .and_then(|l| l.get("list"))
+                .and_then(Value::as_array)
+        };
+
+        let mut touched = HashSet::new();
+        for &(list, id) in tasks {
+            let key = id.to_string();
+            match list_tasks(list).and_then(|t| t.iter().find(|t| t.get("id").and_then(Value::as_str) == Some(&key))) {
+                Some(task) => upsert_task(&tx, list, task)?,
+                None => delete_task(&tx, id)?,
+            }
+            touched.insert(list);
+        }
+        for list in touched {
+            for (position, task) in list_tasks(list).into_iter().flatten().enumerate() {
+                let Some(obj) = task.as_object() else { continue };
+                tx.execute(
+                    "UPDATE tasks SET position = ?1 WHERE id = ?2 AND position != ?1",
+                    params![position as i64, id_of(obj)?],
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn upsert_task(&self, list: ListId, task: &Value) -> Result<(), StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        upsert_task(&tx, list, task)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn delete_task(&self, id: TaskId) -> Result<(), StorageError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        delete_task(&tx, id)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn snapshot_to(&self, target: &Path) -> Result<(), StorageError> {
+        replace_with(target, |tmp| {
+            self.conn().execute("VACUUM INTO ?1", [tmp.to_string_lossy()])?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_and_updates_single_tasks() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = SqliteFile::open(&dir.join("lists.db")).unwrap();
+        let list = ListId::new_v4();
+        let milk = TaskId::new_v4();
+        let doc = json!({
+            "schema_version": 2,
+            "lists": [{ "id": list.to_string(), "title": "Groceries", "list": [
+                { "id": milk.to_string(), "title": "Milk", "status": "Pending" },
+            ]}],
+            "themes": [],
+            "selected_theme": null,
+        });
+
+        db.save(&doc).unwrap();
+        assert_eq!(db.load().unwrap(), Some(doc));
+
+        let eggs = json!({ "id": TaskId::new_v4().to_string(), "title": "Eggs", "status": "Complete" });
+        db.upsert_task(list, &eggs).unwrap();
+        db.delete_task(milk).unwrap();
+        let loaded = db.load().unwrap().unwrap();
+        assert_eq!(loaded["lists"][0]["list"], json!([eggs]));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saving_changed_tasks_matches_a_full_save() {
+        let dir = std::env::temp_dir().join(format!("taskmaster-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lists.db");
+        let db = SqliteFile::open(&path).unwrap();
+        let list = ListId::new_v4();
+        let [a, b, c, d] = [0; 4].map(|_| TaskId::new_v4());
+        let task = |id: TaskId, title: &str| json!({ "id": id.to_string(), "title": title, "status": "Pending" });
+        let doc = |tasks: Vec<Value>, theme: &str| json!({
+            "schema_version": 2,
+            "lists": [{ "id": list.to_string(), "title": "Chores", "list": tasks }],
+            "themes": [],
+            "selected_theme": theme,
+        });
+        db.save(&doc(vec![task(a, "A"), task(b, "B"), task(c, "C")], "Dark")).unwrap();
+
+        let changed = doc(vec![task(d, "D"), task(a, "A2"), task(c, "C")], "Nord");
+        db.save_tasks(&changed, &[(list, b), (list, d), (list, a)]).unwrap();
+
+        assert_eq!(db.load().unwrap(), Some(changed.clone()));
+        let snapshot = dir.join("snapshot.db");
+        db.snapshot_to(&snapshot).unwrap();
+        assert_eq!(SqliteFile::open_read_only(&snapshot).unwrap().load().unwrap(), Some(changed));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
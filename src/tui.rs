@@ -0,0 +1,346 @@
+//! Full-screen terminal frontend for machines where the window cannot open.
+//! Keys are translated into the same `Message`s the window sends, so both
+//! behave alike.
+use crate::cli::describe;
+use crate::models::*;
+#[cfg(unix)]
+use crate::rpc;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List as ListWidget, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::{io, time::Duration};
+use uuid::Uuid;
+
+/// How long to wait for a key before checking the disk and saving.
+const POLL: Duration = Duration::from_millis(500);
+
+const LISTS_HELP: &str =
+    "↑↓ move  Enter open  a add  e rename  d delete  t theme  Ctrl+Z undo  Ctrl+Y redo  q quit";
+const TASKS_HELP: &str =
+    "↑↓ move  Space advance  a add  e edit  d delete  p priority  t theme  Esc back  q quit";
+
+#[derive(Clone, Copy)]
+enum Input {
+    AddList,
+    RenameList,
+    AddTask,
+    EditTask,
+}
+
+struct Tui {
+    app: App,
+    cursor: usize,
+    /// What is being typed, and the text so far.
+    input: Option<(Input, String)>,
+}
+
+/// Runs the terminal frontend until the user quits.
+pub fn run() -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = Tui { app: App::default(), cursor: 0, input: None }.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn color([r, g, b]: Rgb) -> Color {
+    Color::Rgb(r, g, b)
+}
+
+impl Tui {
+    fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        #[cfg(unix)]
+        let rpc = self.listen();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(POLL)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key) {
+                break;
+            }
+            #[cfg(unix)]
+            for request in rpc.iter().flat_map(|(_, requests)| requests.try_iter()) {
+                self.send(Message::Rpc(request));
+            }
+            self.send(Message::CheckDisk);
+            // The window saves from a background task after `Tick`; here the
+            // write happens inline, so `Tick` is left with expiring the toast.
+            if self.app.conflict.is_none() {
+                self.app.flush();
+            }
+            self.send(Message::Tick);
+        }
+        self.app.close();
+        Ok(())
+    }
+
+    /// Opens the workspace's control socket, as the window does.
+    #[cfg(unix)]
+    fn listen(&self) -> Option<(rpc::Listener, std::sync::mpsc::Receiver<rpc::Request>)> {
+        if self.app.read_only {
+            return None;
+        }
+        let path = crate::core::data_path(&self.app.workspace)?;
+        let (sender, requests) = std::sync::mpsc::channel();
+        let listener = rpc::listen(&rpc::socket_path(&path), move |request| sender.send(request).is_ok()).ok()?;
+        Some((listener, requests))
+    }
+
+    fn send(&mut self, msg: Message) {
+        self.app.update(msg);
+    }
+
+    fn selected(&self) -> Option<&Tasks> {
+        self.app.selected.and_then(|id| self.app.doc.list(id))
+    }
+
+    /// Ids and labels of the rows on screen, in display order.
+    fn rows(&self) -> Vec<(Uuid, String)> {
+        match self.selected() {
+            Some(list) => list.display_order(&self.app.list_editor.tag_filter)
+                .into_iter()
+                .map(|i| &list.list[i])
+                .map(|task| {
+                    let (done, total) = task.progress();
+                    let mut label = describe(task);
+                    if total > 0 {
+                        label.push_str(&format!("  ({done}/{total})"));
+                    }
+                    (task.id, label)
+                })
+                .collect(),
+            None => self.app.doc.lists
+                .iter()
+                .map(|list| {
+                    let done = list.list.iter().filter(|t| t.status == Status::Complete).count();
+                    (list.id, format!("{} ({done}/{})", list.title, list.list.len()))
+                })
+                .collect(),
+        }
+    }
+
+    fn current(&self) -> Option<Uuid> {
+        self.rows().get(self.cursor).map(|(id, _)| *id)
+    }
+
+    /// Handles one key press. Returns false when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.input.is_some() {
+            self.handle_input(key.code);
+            return true;
+        }
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return false,
+            KeyCode::Char('z') | KeyCode::Char('Z') if ctrl && shift => self.send(Message::Redo),
+            KeyCode::Char('z') if ctrl => self.send(Message::Undo),
+            KeyCode::Char('y') if ctrl => self.send(Message::Redo),
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('u') => self.send(Message::Undo),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor += 1,
+            KeyCode::Char('t') => {
+                let themes = &self.app.doc.themes;
+                let next = self.app.doc.selected_theme
+                    .and_then(|theme| themes.iter().position(|t| *t == theme))
+                    .map_or(0, |i| (i + 1) % themes.len().max(1));
+                if let Some(theme) = themes.get(next).copied() {
+                    self.send(Message::ThemeChanged(theme));
+                }
+            }
+            KeyCode::Char('K') if self.app.conflict.is_some() => self.send(Message::KeepMine),
+            KeyCode::Char('T') if self.app.conflict.is_some() => self.send(Message::TakeTheirs),
+            KeyCode::Char('M') if self.app.conflict.is_some() => self.send(Message::MergeChanges),
+            KeyCode::Char('R') if self.app.recovery.is_some() => self.send(Message::RestoreBackup),
+            code if self.app.selected.is_some() => self.handle_task_key(code),
+            code => self.handle_list_key(code),
+        }
+        self.cursor = self.cursor.min(self.rows().len().saturating_sub(1));
+        true
+    }
+
+    fn handle_list_key(&mut self, code: KeyCode) {
+        let current = self.current();
+        match (code, current) {
+            (KeyCode::Enter | KeyCode::Right | KeyCode::Char('l'), Some(id)) => {
+                self.send(Message::SelectList(id));
+                self.cursor = 0;
+            }
+            (KeyCode::Char('a'), _) => {
+                self.send(Message::AddListAfter(self.app.doc.lists.len()));
+                self.input = Some((Input::AddList, String::new()));
+            }
+            (KeyCode::Char('e'), Some(id)) => {
+                let title = self.app.doc.list(id).map(|l| l.title.clone()).unwrap_or_default();
+                self.send(Message::ChangeListTitle(id));
+                self.send(Message::UpdateListTitle(title.clone()));
+                self.input = Some((Input::RenameList, title));
+            }
+            (KeyCode::Char('d') | KeyCode::Delete, Some(id)) => self.send(Message::RemoveList(id)),
+            _ => {}
+        }
+    }
+
+    fn handle_task_key(&mut self, code: KeyCode) {
+        let current = self.current();
+        match (code, current) {
+            (KeyCode::Esc | KeyCode::Left | KeyCode::Char('h') | KeyCode::Backspace, _) => {
+                let list = self.app.selected;
+                self.send(Message::BackToLists);
+                self.cursor = self.app.doc.lists.iter().position(|l| Some(l.id) == list).unwrap_or(0);
+            }
+            (KeyCode::Char(' ') | KeyCode::Enter, Some(id)) => self.send(Message::Forward(id)),
+            (KeyCode::Char('a'), _) => {
+                let end = self.selected().map_or(0, |l| l.list.len());
+                self.send(Message::AddAfter(end));
+                self.input = Some((Input::AddTask, String::new()));
+            }
+            (KeyCode::Char('e'), Some(id)) => {
+                let title = self.selected()
+                    .and_then(|l| l.task(id))
+                    .map(|t| t.title.clone())
+                    .unwrap_or_default();
+                self.send(Message::ChangeTitle(id));
+                self.send(Message::UpdateNewTitle(title.clone()));
+                self.input = Some((Input::EditTask, title));
+            }
+            (KeyCode::Char('d') | KeyCode::Delete, Some(id)) => self.send(Message::Remove(id)),
+            (KeyCode::Char('p'), Some(id)) => {
+                let priority = self.selected().and_then(|l| l.task(id)).map_or(Priority::None, |t| t.priority);
+                let next = Priority::ALL.iter().position(|p| *p == priority).map_or(0, |i| i + 1);
+                self.send(Message::SetPriority(id, Priority::ALL[next % Priority::ALL.len()]));
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_input(&mut self, code: KeyCode) {
+        let Some((input, mut text)) = self.input.take() else {
+            return;
+        };
+        let (confirm, cancel) = match input {
+            Input::AddList => (Message::ConfirmAddList, Message::CancelAddList),
+            Input::RenameList => (Message::ConfirmListEdit, Message::CancelListEdit),
+            Input::AddTask => (Message::ConfirmAdd, Message::CancelAdd),
+            Input::EditTask => (Message::ConfirmEdit, Message::CancelEdit),
+        };
+        match code {
+            KeyCode::Enter => {
+                self.send(confirm);
+                // A rejected title stays in the prompt, next to the error.
+                if self.app.error.is_some() || self.app.list_editor.error.is_some() {
+                    self.input = Some((input, text));
+                }
+                return;
+            }
+            KeyCode::Esc => return self.send(cancel),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Char(c) => text.push(c),
+            _ => {}
+        }
+        self.send(match input {
+            Input::AddList | Input::RenameList => Message::UpdateListTitle(text.clone()),
+            Input::AddTask | Input::EditTask => Message::UpdateNewTitle(text.clone()),
+        });
+        self.input = Some((input, text));
+    }
+
+    /// One line per banner the window would show, plus the toast.
+    fn notices(&self) -> Vec<String> {
+        let mut notices = Vec::new();
+        if self.app.read_only {
+            notices.push(String::from("Read-only: this workspace is open in another window."));
+        }
+        if let Some(recovery) = &self.app.recovery {
+            let mut notice = String::from("The lists file could not be read.");
+            if recovery.backup.is_some() {
+                notice.push_str(" R restores the newest backup.");
+            }
+            notices.push(notice);
+        }
+        if let Some(error) = self.app.error.as_ref().or(self.app.list_editor.error.as_ref()) {
+            notices.push(error.clone());
+        }
+        if self.app.conflict.is_some() {
+            notices.push(String::from("Changed on disk while you had unsaved edits: K keep mine, T take theirs, M merge."));
+        }
+        if let Some((notice, _)) = &self.app.toast {
+            notices.push(format!("{notice}. Ctrl+Z to undo."));
+        }
+        notices
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let palette = self.app.doc.selected_theme.unwrap_or_default().palette();
+        let base = Style::default().fg(color(palette.text)).bg(color(palette.background));
+        let accent = color(palette.primary);
+        let notices = self.notices();
+        let [header, body, footer] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(notices.len() as u16 + 3),
+        ])
+        .areas(frame.area());
+        frame.render_widget(Block::default().style(base), frame.area());
+
+        let title = match self.selected() {
+            Some(list) => list.title.clone(),
+            None => String::from("Lists"),
+        };
+        let theme = self.app.doc.selected_theme.unwrap_or(Themes::Default);
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(" TaskMaster ", Style::default().fg(accent).add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  {}  ·  {theme:?}", self.app.workspace)),
+            ])),
+            header,
+        );
+
+        let tasks = self.selected();
+        let items: Vec<ListItem> = self.rows()
+            .into_iter()
+            .map(|(id, label)| {
+                let style = match tasks.and_then(|l| l.task(id)).map(|t| t.status) {
+                    Some(Status::Complete) => Style::default().fg(color(palette.success)),
+                    Some(Status::InProgress) => Style::default().fg(accent),
+                    _ => Style::default(),
+                };
+                ListItem::new(label).style(style)
+            })
+            .collect();
+        let list = ListWidget::new(items)
+            .block(Block::bordered().title(format!(" {title} ")))
+            .highlight_style(Style::default().fg(color(palette.background)).bg(accent))
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(self.cursor));
+        frame.render_stateful_widget(list, body, &mut state);
+
+        let mut lines: Vec<Line> = notices
+            .into_iter()
+            .map(|notice| Line::styled(notice, Style::default().fg(color(palette.danger))))
+            .collect();
+        lines.push(match &self.input {
+            Some((input, text)) => {
+                let prompt = match input {
+                    Input::AddList => "New list",
+                    Input::RenameList => "Rename list",
+                    Input::AddTask => "New task",
+                    Input::EditTask => "Edit task",
+                };
+                Line::from(format!("{prompt}: {text}_   (Enter to save, Esc to cancel)"))
+            }
+            None if tasks.is_some() => Line::from(TASKS_HELP),
+            None => Line::from(LISTS_HELP),
+        });
+        frame.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::TOP)), footer);
+    }
+}